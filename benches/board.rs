@@ -0,0 +1,160 @@
+//! Compares the bitboard against scanning cells the way the board used to, and measures how fast
+//! games can be simulated. Run with `cargo bench -p tetris --bench board`
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use rand::{prelude::*, rngs::StdRng};
+use tetris::{
+    Board, Cell, Config, Event, Game, GameState, InputEvent, Piece, PieceLocation, Rotation,
+    sound::{NullSink, SoundPlayer},
+};
+
+const PIECES: [Piece; 7] = [Piece::I, Piece::J, Piece::L, Piece::O, Piece::S, Piece::T, Piece::Z];
+const ROTATIONS: [Rotation; 4] = [Rotation::North, Rotation::East, Rotation::South, Rotation::West];
+
+/// Runs `f` for about a second and returns the average time per run
+fn bench(name: &str, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        f();
+        runs += 1;
+    }
+    let each = start.elapsed() / runs;
+    println!("{name:<28} {each:>12.2?}");
+    each
+}
+
+fn compare(name: &str, cells: impl FnMut(), bitboard: impl FnMut()) {
+    let before = bench(&format!("{name} (cells)"), cells);
+    let after = bench(&format!("{name} (bitboard)"), bitboard);
+    println!("{:<28} {:>11.1}x\n", "speedup", before.as_secs_f64() / after.as_secs_f64());
+}
+
+fn fits_cells(board: &Board, pos: [(i8, i8); 4]) -> bool {
+    pos.into_iter().all(|(x, y)| {
+        (0..10).contains(&x) && (0..30).contains(&y) && board[y as usize][x as usize] == Cell::Empty
+    })
+}
+
+fn ghost_cells(board: &Board, mut pos: [(i8, i8); 4]) -> [(i8, i8); 4] {
+    loop {
+        let next = pos.map(|(x, y)| (x, y - 1));
+        if !fits_cells(board, next) {
+            return pos;
+        }
+        pos = next;
+    }
+}
+
+/// A messy stack like the lab's search has to deal with
+fn stack(rng: &mut StdRng) -> Board {
+    let mut board = Board::default();
+    for x in 0..10 {
+        for y in 0..rng.random_range(0..12) {
+            if rng.random_bool(0.8) {
+                board.set(x, y, Cell::Garbage);
+            }
+        }
+    }
+    board
+}
+
+/// Every spot a piece could be dropped from at the top of the board
+fn drops() -> Vec<[(i8, i8); 4]> {
+    let mut drops = vec![];
+    for piece in PIECES {
+        for rot in ROTATIONS {
+            for x in -2..12 {
+                let blocks = PieceLocation::new(piece, (x, 20), rot).blocks();
+                if blocks.iter().all(|&(x, _)| (0..10).contains(&x)) {
+                    drops.push(blocks);
+                }
+            }
+        }
+    }
+    drops
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let boards: Vec<_> = (0..64).map(|_| stack(&mut rng)).collect();
+    let drops = drops();
+
+    compare(
+        "ghost pieces",
+        || {
+            for board in &boards {
+                for &blocks in &drops {
+                    black_box(ghost_cells(board, blocks));
+                }
+            }
+        },
+        || {
+            for board in &boards {
+                for &blocks in &drops {
+                    black_box(board.drop_distance(blocks));
+                }
+            }
+        },
+    );
+
+    compare(
+        "full rows",
+        || {
+            for board in &boards {
+                let full = (0..30).filter(|&y| board[y][..10].iter().all(|&c| c != Cell::Empty));
+                black_box(full.count());
+            }
+        },
+        || {
+            for board in &boards {
+                black_box(board.full_rows(10));
+            }
+        },
+    );
+
+    compare(
+        "tetrizz columns",
+        || {
+            for board in &boards {
+                let mut cols = [0u64; 10];
+                for (x, col) in cols.iter_mut().enumerate() {
+                    for y in 0..50 {
+                        if board[y][x] != Cell::Empty {
+                            *col |= 1 << y;
+                        }
+                    }
+                }
+                black_box(cols);
+            }
+        },
+        || {
+            for board in &boards {
+                black_box(std::array::from_fn::<_, 10, _>(|x| board.column(x)));
+            }
+        },
+    );
+
+    // random hard drops until the game ends, then start another one
+    let sound = SoundPlayer::<NullSink>::default();
+    let mut game = Game::new(Config { gravity: None, ..Default::default() });
+    let each = bench("simulated placement", || {
+        if game.state != GameState::Running {
+            game.start(Some(rng.random()), &sound);
+            let (time, start) = game.timers.pop_front().unwrap();
+            game.handle(Event::Timer(start), time, &sound);
+        }
+        let piece = game.current.piece;
+        let (x, rot) = (rng.random_range(0..10), ROTATIONS[rng.random_range(0..4)]);
+        let moved = PieceLocation::new(piece, (x, game.current.pos.1), rot);
+        if game.check_valid(moved.blocks()) {
+            game.current = moved;
+        }
+        black_box(game.handle(Event::Input(InputEvent::Hard), game.time, &sound));
+    });
+    println!("{:<28} {:>10.0}/s", "placements", 1.0 / each.as_secs_f64());
+}
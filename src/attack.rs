@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use strum::EnumString;
+
+use crate::{PlacementResult, SpinKind};
+
+/// Which game's rules to use for how much garbage a clear sends
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum AttackTable {
+    #[default]
+    Guideline,
+    Jstris,
+    /// season 2 rules, with multiplicative combos and b2b surge
+    Tetrio,
+}
+
+const GUIDELINE_COMBO: [u16; 11] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+const JSTRIS_COMBO: [u16; 13] = [0, 0, 1, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+const PERFECT_CLEAR: u16 = 10;
+/// tetr.io starts charging a surge once the b2b chain gets to x4
+const SURGE_THRESHOLD: u16 = 4;
+
+impl AttackTable {
+    /// `prev_b2b` is the length of the b2b chain before this placement, for surge
+    pub fn attack(&self, placement: &PlacementResult, prev_b2b: u16) -> u16 {
+        if placement.rows.is_empty() {
+            return 0;
+        }
+        let base = match (placement.rows.len(), placement.spin) {
+            (1, SpinKind::None | SpinKind::Mini) => 0,
+            (2, SpinKind::Mini) => 1,
+            (n, SpinKind::None) => [1, 2, 4][n.min(4) - 2],
+            (n, _) => 2 * n.min(3) as u16,
+        };
+        // b2b only counts once there's a previous difficult clear to chain from
+        let b2b = u16::from(placement.b2b > 1);
+        // combo counts from 0 on the first clear of a chain
+        let combo = placement.combo.saturating_sub(1) as usize;
+        let pc = if placement.perfect_clear { PERFECT_CLEAR } else { 0 };
+        match self {
+            AttackTable::Guideline => {
+                base + b2b + GUIDELINE_COMBO[combo.min(GUIDELINE_COMBO.len() - 1)] + pc
+            }
+            AttackTable::Jstris => {
+                base + b2b + JSTRIS_COMBO[combo.min(JSTRIS_COMBO.len() - 1)] + pc
+            }
+            AttackTable::Tetrio => {
+                let attack = (base + b2b) as f32;
+                let attack = match combo {
+                    0 => attack,
+                    _ if attack > 0.0 => attack * (1.0 + 0.25 * combo as f32),
+                    _ => (1.0 + 1.25 * combo as f32).ln(),
+                };
+                let prev_level = prev_b2b.saturating_sub(1);
+                let surge = if placement.b2b == 0 && prev_level >= SURGE_THRESHOLD {
+                    prev_level
+                } else {
+                    0
+                };
+                attack as u16 + surge + pc
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::ops::Index;
+
+use crate::{Cell, Pos};
+
+/// widest board that fits in a [`Board`], see [`Config::width`](crate::Config::width)
+pub const MAX_WIDTH: usize = 16;
+/// tallest visible board that fits in a [`Board`], see [`Config::height`](crate::Config::height)
+pub const MAX_HEIGHT: usize = 40;
+/// rows above the visible board that pieces can still move and lock in
+pub(crate) const BUFFER: usize = 10;
+const ROWS: usize = MAX_HEIGHT + BUFFER;
+
+/// The cells on the board along with a bitboard of which ones are filled. Only the bottom left
+/// `width` by `height + 10` cells get used
+///
+/// Cells can be read with `board[y][x]` but have to be written with [`Board::set`] to keep the
+/// bitboard in sync
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Board {
+    cells: [[Cell; MAX_WIDTH]; ROWS],
+    /// bit `y` of column `x` is set when that cell is filled, the same layout tetrizz uses
+    cols: [u64; MAX_WIDTH],
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self { cells: [[Cell::Empty; MAX_WIDTH]; ROWS], cols: [0; MAX_WIDTH] }
+    }
+}
+
+impl Index<usize> for Board {
+    type Output = [Cell; MAX_WIDTH];
+
+    fn index(&self, y: usize) -> &Self::Output {
+        &self.cells[y]
+    }
+}
+
+impl Board {
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        self.cells[y][x] = cell;
+        if cell == Cell::Empty {
+            self.cols[x] &= !(1 << y);
+        } else {
+            self.cols[x] |= 1 << y;
+        }
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, [Cell; MAX_WIDTH]> {
+        self.cells.iter()
+    }
+
+    /// The occupancy of column `x`, with bit `y` set for each filled cell
+    pub fn column(&self, x: usize) -> u64 {
+        self.cols[x]
+    }
+
+    pub fn is_filled(&self, x: usize, y: usize) -> bool {
+        self.cols[x] >> y & 1 == 1
+    }
+
+    /// Whether all the blocks are inside the `width` by `rows` area and on empty cells
+    pub fn fits(&self, pos: Pos, width: usize, rows: usize) -> bool {
+        pos.into_iter().all(|(x, y)| {
+            (0..width as i8).contains(&x)
+                && (0..rows as i8).contains(&y)
+                && !self.is_filled(x as usize, y as usize)
+        })
+    }
+
+    /// How far the blocks can fall before landing, assuming they fit where they are
+    pub fn drop_distance(&self, pos: Pos) -> i8 {
+        pos.into_iter()
+            .map(|(x, y)| {
+                let below = self.cols[x as usize] & ((1u64 << y) - 1);
+                // distance to the highest filled cell below, or to the floor
+                y - (u64::BITS - below.leading_zeros()) as i8
+            })
+            .min()
+            .unwrap()
+    }
+
+    /// Bit `y` is set for each row that's filled in all of the first `width` columns
+    pub fn full_rows(&self, width: usize) -> u64 {
+        self.cols[..width].iter().fold(!0, |full, col| full & col)
+    }
+
+    /// Whether there's nothing on the board other than the rows in `mask`
+    pub fn is_empty_except(&self, mask: u64) -> bool {
+        self.cols.iter().all(|col| col & !mask == 0)
+    }
+
+    /// Removes row `y`, moving everything above it down one
+    pub fn clear_row(&mut self, y: usize) {
+        self.cells.copy_within(y + 1.., y);
+        self.cells[ROWS - 1] = [Cell::Empty; MAX_WIDTH];
+        let below = (1u64 << y) - 1;
+        for col in &mut self.cols {
+            *col = *col & below | *col >> 1 & !below;
+        }
+    }
+
+    /// Pushes everything up by `lines` and fills the bottom with copies of `row`. Anything pushed
+    /// past `rows` is gone
+    pub fn insert_rows(&mut self, lines: usize, row: [Cell; MAX_WIDTH], rows: usize) {
+        let lines = lines.min(rows);
+        self.cells.copy_within(..rows - lines, lines);
+        self.cells[..lines].fill(row);
+        let (kept, filled) = ((1u64 << rows) - 1, (1u64 << lines) - 1);
+        for (col, &cell) in self.cols.iter_mut().zip(&row) {
+            *col = *col << lines & kept | if cell == Cell::Empty { 0 } else { filled };
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use web_time::Instant;
+
+use crate::{
+    Event, FRAME, Game, InputEvent, PlacementResult,
+    sound::{Sink, SoundPlayer},
+};
+
+/// Makes up timestamps from a frame count instead of reading the wall clock. The game only ever
+/// looks at the time between timestamps, so a game driven by this plays out the same way for the
+/// same inputs no matter where the clock starts or how fast it gets stepped
+#[derive(Copy, Clone, Debug)]
+pub struct FrameClock {
+    origin: Instant,
+    pub frame: u64,
+}
+
+impl FrameClock {
+    /// Frame 0 is at `origin`, which can be any instant
+    pub fn new(origin: Instant) -> Self {
+        Self { origin, frame: 0 }
+    }
+
+    pub fn now(&self) -> Instant {
+        self.at(self.frame)
+    }
+
+    pub fn at(&self, frame: u64) -> Instant {
+        self.origin + Duration::from_nanos(FRAME.as_nanos() as u64 * frame)
+    }
+
+    /// Starts the game on the current frame
+    pub fn start(&self, game: &mut Game, seed: u64, sound: &SoundPlayer<impl Sink>) {
+        game.start_at(Some(seed), self.now(), sound);
+    }
+
+    /// Handles an input on the current frame
+    pub fn input(
+        &self,
+        game: &mut Game,
+        input: InputEvent,
+        sound: &SoundPlayer<impl Sink>,
+    ) -> Option<PlacementResult> {
+        game.handle(Event::Input(input), self.now(), sound)
+    }
+
+    /// Moves on by `frames`, firing every timer that comes due along the way
+    pub fn step(
+        &mut self,
+        game: &mut Game,
+        frames: u64,
+        sound: &SoundPlayer<impl Sink>,
+    ) -> Vec<PlacementResult> {
+        self.frame += frames;
+        game.advance(self.now(), sound)
+    }
+}
@@ -6,13 +6,23 @@ use tetrizz::beam_search::Node;
 use web_time::{Instant, SystemTime};
 
 use crate::{
+    board::{BUFFER, Board, MAX_WIDTH},
+    gravity::Timings,
+    randomizer::random_seed,
     sound::{Action, Clear, Meta, Sink, Sound, SoundPlayer},
     *,
 };
 
-pub type Board = [[Cell; 10]; 50]; // hope no one stacks higher than this 👀
+const LINES_PER_LEVEL: u16 = 10;
+const MASTER_MAX_LEVEL: u16 = 999;
+/// room for a 14-bag on top of the 6 pieces left when the queue gets refilled
+const QUEUE: usize = 32;
+/// most pieces that can be held at once, see [`Config::hold_slots`]
+pub const MAX_HOLD_SLOTS: u8 = 3;
+/// longest preview, see [`Config::preview`]. The queue never runs shorter than this
+pub const MAX_PREVIEW: u8 = 7;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Lookahead {
     /// number of placements before board will render again
     pub min_placements: usize,
@@ -34,12 +44,34 @@ impl Lookahead {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Mode {
     Sprint {
         target_lines: u16,
     },
-    // Cheese { target_lines: u16 },
+    /// dig through `target_lines` rows of garbage, with up to `height` of them on the board at once
+    Cheese {
+        target_lines: u16,
+        height: u8,
+    },
+    /// clear `target_lines` while the level goes up every 10 lines, speeding the game up
+    Marathon {
+        target_lines: u16,
+        gravity: GravityCurve,
+        /// `(level, frames)` pairs that replace the first lock delay from that level onwards
+        lock_delay: Vec<(u16, u16)>,
+    },
+    /// tgm style, the level goes up with every piece and line and the game ends at 999. Gravity
+    /// reaches 20G halfway through, then the delays start shrinking every section
+    Master {
+        /// state: stops at the end of each section until a line is cleared
+        level: u16,
+    },
+    /// score as many points as possible before time runs out
+    Ultra {
+        /// unit: seconds
+        duration: u16,
+    },
     TrainingLab {
         lookahead: Option<Lookahead>,
         search: bool,
@@ -48,28 +80,48 @@ pub enum Mode {
     },
 }
 
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Sprint { target_lines: 40 }
+    }
+}
+
 impl Mode {
-    pub fn is_complete(&self, lines: u16) -> bool {
-        match self {
-            &Mode::Sprint { target_lines } => lines >= target_lines,
-            _ => false,
-        }
+    /// Whether the first bag gets rerolled when it starts with an S or Z, unless the config says
+    /// otherwise
+    fn no_sz_start(&self) -> bool {
+        matches!(self, Mode::Sprint { .. })
     }
 
     fn allows_undo(&self) -> bool {
         match self {
-            Mode::Sprint { .. } => false,
+            Mode::Sprint { .. }
+            | Mode::Cheese { .. }
+            | Mode::Marathon { .. }
+            | Mode::Master { .. }
+            | Mode::Ultra { .. } => false,
             Mode::TrainingLab { .. } => true,
         }
     }
 
     pub fn search_enabled(&self) -> bool {
         match self {
-            Mode::Sprint { .. } => false,
+            Mode::Sprint { .. }
+            | Mode::Cheese { .. }
+            | Mode::Marathon { .. }
+            | Mode::Master { .. }
+            | Mode::Ultra { .. } => false,
             Mode::TrainingLab { search, .. } => *search,
         }
     }
 
+    pub fn time_limit(&self) -> Option<Duration> {
+        match self {
+            Mode::Ultra { duration } => Some(Duration::from_secs(*duration as u64)),
+            _ => None,
+        }
+    }
+
     fn lookahead_timeout(&self) -> u16 {
         match self {
             Mode::TrainingLab { lookahead: Some(lookahead), .. } => lookahead.timeout,
@@ -78,31 +130,168 @@ impl Mode {
     }
 
     fn start(&mut self) {
-        if let Mode::TrainingLab { lookahead: Some(lookahead), .. } = self {
-            lookahead.board_visible = true;
-            lookahead.next_piece_goal = lookahead.min_placements;
+        match self {
+            Mode::TrainingLab { lookahead: Some(lookahead), .. } => {
+                lookahead.board_visible = true;
+                lookahead.next_piece_goal = lookahead.min_placements;
+            }
+            Mode::Master { level } => *level = 0,
+            _ => {}
+        }
+    }
+}
+
+/// Why the game was lost
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TopOut {
+    /// the next piece overlapped the stack when it tried to spawn
+    BlockOut,
+    /// a piece locked entirely above the visible board
+    LockOut,
+    /// a piece locked partly above the visible board, with [`LockOut::Partial`]
+    PartialLockOut,
+    /// garbage pushed the stack out of the top of the board, or left no room for the piece
+    GarbageOut,
+}
+
+impl fmt::Display for TopOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TopOut::BlockOut => "block out",
+            TopOut::LockOut => "lock out",
+            TopOut::PartialLockOut => "partial lock out",
+            TopOut::GarbageOut => "garbage out",
+        })
+    }
+}
+
+/// How often the current piece can be held
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum HoldRule {
+    /// no hold at all, like classic tetris
+    None,
+    /// once until the next piece spawns, so a piece that comes out of hold can't go back in
+    #[default]
+    Once,
+    /// as often as you like
+    Unlimited,
+}
+
+/// When locking a piece above the visible board ends the game. Whatever's left of the piece
+/// after its line clears is what counts
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum LockOut {
+    /// pieces can lock anywhere in the buffer above the board
+    None,
+    /// the guideline rule, only when all of the piece is above the board
+    #[default]
+    Full,
+    /// when any of the piece is above the board
+    Partial,
+}
+
+/// Everything that happened as a result of a piece locking into the board
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlacementResult {
+    pub location: PieceLocation,
+    /// indices of the rows that were filled, bottom up, before they got cleared
+    pub rows: Vec<usize>,
+    pub spin: SpinKind,
+    pub perfect_clear: bool,
+    /// the combo and back-to-back chains including this placement, see [`Game::combo`]
+    pub combo: u16,
+    pub b2b: u16,
+    /// lines of garbage sent according to the configured [`AttackTable`]
+    pub attack: u16,
+    /// guideline points for the clear times the level, drop points go straight to
+    /// [`Game::score`]
+    pub score: u32,
+    /// how much of the attack went towards cancelling pending garbage instead of being sent
+    pub cancelled: u16,
+    /// set if the piece locked out or the next piece couldn't spawn. With an entry delay the next
+    /// piece spawns after the placement is over, so a block out there only shows up in
+    /// [`Game::state`]
+    pub top_out: Option<TopOut>,
+}
+
+impl PlacementResult {
+    pub fn sound(&self) -> Sound {
+        match (self.rows.len(), self.spin) {
+            (0, _) => Action::Lock.into(), // TODO: differentiate lock/harddrop
+            (1, SpinKind::None) => Clear::Single.into(),
+            (1, _) => Clear::Tspin.into(),
+            (2, SpinKind::None) => Clear::Double.into(),
+            (2, _) => Clear::TspinDouble.into(),
+            (3, SpinKind::None) => Clear::Triple.into(),
+            (3, _) => Clear::TSpinTriple.into(),
+            (4, _) => Clear::Quad.into(),
+            _ => unreachable!("impossible line clear"),
+        }
+    }
+}
+
+/// The name of the clear, like "Mini T-Spin Single", empty for a placement that did nothing
+impl fmt::Display for PlacementResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        match self.spin {
+            SpinKind::None => {}
+            SpinKind::Mini => parts.push(format!("Mini {:?}-Spin", self.location.piece)),
+            SpinKind::Full => parts.push(format!("{:?}-Spin", self.location.piece)),
+        }
+        match self.rows.len() {
+            0 => {}
+            1 => parts.push("Single".to_owned()),
+            2 => parts.push("Double".to_owned()),
+            3 => parts.push("Triple".to_owned()),
+            _ => parts.push("Quad".to_owned()),
         }
+        if self.perfect_clear {
+            parts.push("Perfect Clear".to_owned());
+        }
+        f.write_str(&parts.join(" "))
     }
 }
 
 #[derive(Clone)]
 pub struct Moment {
-    pub board: [[Cell; 10]; 50], // hope no one stacks higher than this 👀
+    pub board: Board,
     pub current: PieceLocation,
-    pub hold: Option<Piece>,
-    pub upcomming: ConstGenericRingBuffer<Piece, 14>,
+    pub hold: Vec<Piece>,
+    pub upcomming: ConstGenericRingBuffer<Piece, QUEUE>,
     pub spins: Vec<Node>,
     pub pieces_placed: usize,
+    pub lines: u16,
+    pub garbage_cleared: u16,
+    pub combo: u16,
+    pub b2b: u16,
+    pub attack: u32,
+    pub score: u32,
+    pub pending_garbage: VecDeque<(Instant, u8)>,
 }
 
 #[derive(Clone)]
 pub struct Game {
     pub board: Board,
-    pub upcomming: ConstGenericRingBuffer<Piece, 14>,
+    pub upcomming: ConstGenericRingBuffer<Piece, QUEUE>,
     pub current: PieceLocation,
-    pub hold: Option<Piece>,
+    /// held pieces, oldest first since that's the one that comes back out
+    pub hold: Vec<Piece>,
     pub lines: u16,
+    /// lines cleared that had garbage in them, which is what cheese mode counts
+    pub garbage_cleared: u16,
     pub pieces: usize,
+    /// number of placements in a row that cleared lines, so a "1 combo" is 2
+    pub combo: u16,
+    /// number of quads and spin clears in a row, not broken by placements that don't clear
+    pub b2b: u16,
+    /// total lines of garbage sent, not counting what went towards cancelling incoming garbage
+    pub attack: u32,
+    pub score: u32,
     pub mode: Mode,
     pub config: Config,
     pub timers: VecDeque<(Instant, TimerEvent)>,
@@ -111,14 +300,31 @@ pub struct Game {
     pub started_left: Option<Instant>,
     pub start_time: Option<Instant>,
     pub end_time: Option<Instant>,
+    /// when the game was paused, frontends shouldn't fire timers until it's resumed
+    pub paused: Option<Instant>,
     pub soft_dropping: bool,
     pub can_hold: bool,
-    pub spin: bool, // TODO: detection
+    pub last_placement: Option<PlacementResult>,
+    /// direction and kick index of the last rotation, cleared whenever the piece moves
+    pub last_kick: Option<(Spin, usize)>,
+    /// fraction of a row that gravity between whole rows per frame has built up
+    pub fall: f32,
     pub state: GameState,
+    /// full rows that stay on the board until the line clear delay is over
+    pub clearing: Vec<usize>,
+    /// inputs made during ARE that get applied once the next piece spawns
+    pub buffered: Vec<InputEvent>,
+    /// received garbage that hasn't risen yet, along with when it's allowed to
+    pub pending_garbage: VecDeque<(Instant, u8)>,
     pub rng: StdRng,
+    pub randomizer: Box<dyn Randomizer>,
+    /// separate from `rng` so garbage holes don't change the piece sequence
+    pub garbage_rng: StdRng,
     pub spins: Vec<Node>,
     pub solution: Option<(Node, Box<Game>)>,
     pub history: VecDeque<Moment>,
+    /// moments that were undone since the last placement, the most recently undone last
+    pub redo: Vec<Moment>,
 }
 
 struct SpinFormatter<'a>(&'a Game);
@@ -175,30 +381,132 @@ impl fmt::Display for SpinFormatter<'_> {
 }
 
 impl Game {
+    /// Only the pieces in the preview are passed on, so the search can't see further ahead than
+    /// the player can
     pub fn as_tetrizz_game_and_queue(&self) -> (tetrizz::data::Game, Vec<tetrizz::data::Piece>) {
         let mut queue: Vec<_> = std::iter::once(self.current.piece.into())
-            .chain(self.upcomming.iter().cloned().map(Into::into))
+            .chain(self.upcomming.iter().take(self.preview()).cloned().map(Into::into))
             .collect();
+        let hold = match self.hold.first() {
+            Some(&piece) => piece.into(),
+            // tetrizz always has a hold, so without one it gets the current piece, which changes
+            // nothing until it's used and `can_follow` throws out suggestions that use it. The
+            // same goes when holding would bring out a piece nobody can see yet
+            None if self.hold_slots() == 0 || queue.len() == 1 => queue[0],
+            None => queue.remove(0),
+        };
         let game = tetrizz::data::Game {
             board: self.as_tetrizz_board(),
-            hold: self.hold.map(Into::into).unwrap_or_else(|| queue.remove(0)),
-            b2b: 0,
-            b2b_deficit: 0,
+            // the search only knows about a single hold slot
+            hold,
+            b2b: self.b2b.saturating_sub(1) as _,
+            b2b_deficit: 0, // TODO: find out what tetrizz expects here
         };
         (game, queue)
     }
     fn as_tetrizz_board(&self) -> tetrizz::data::Board {
         let mut board = tetrizz::data::Board { cols: [tetrizz::data::Column(0); 10] };
-        for column in 0..10 {
-            for row in 0..50 {
-                if self.board[row][column] != Cell::Empty {
-                    board.cols[column].0 |= 1 << row;
-                }
-            }
+        for (column, col) in board.cols.iter_mut().enumerate() {
+            // tetrizz only knows about 10 wide boards, so narrower ones get walled off
+            col.0 = if column < self.width() { self.board.column(column) as _ } else { !0 };
         }
         board
     }
 
+    pub fn is_complete(&self) -> bool {
+        match self.mode {
+            Mode::Sprint { target_lines } | Mode::Marathon { target_lines, .. } => {
+                self.lines >= target_lines
+            }
+            Mode::Cheese { target_lines, .. } => self.garbage_cleared >= target_lines,
+            Mode::Master { level } => level >= MASTER_MAX_LEVEL,
+            Mode::Ultra { .. } => self.time_remaining(self.time).is_some_and(|t| t.is_zero()),
+            Mode::TrainingLab { .. } => false,
+        }
+    }
+
+    /// How many more lines need to be cleared in modes that have a goal
+    pub fn lines_remaining(&self) -> Option<u16> {
+        match self.mode {
+            Mode::Sprint { target_lines } | Mode::Marathon { target_lines, .. } => {
+                Some(target_lines.saturating_sub(self.lines))
+            }
+            Mode::Cheese { target_lines, .. } => {
+                Some(target_lines.saturating_sub(self.garbage_cleared))
+            }
+            Mode::Master { .. } | Mode::Ultra { .. } | Mode::TrainingLab { .. } => None,
+        }
+    }
+
+    /// The current level in modes that speed up, starting from 1
+    pub fn level(&self) -> Option<u16> {
+        match self.mode {
+            Mode::Marathon { .. } => Some(1 + self.lines / LINES_PER_LEVEL),
+            Mode::Master { level } => Some(level),
+            _ => None,
+        }
+    }
+
+    /// Gravity from the mode's curve, overriding the configured gravity
+    ///
+    /// unit: rows per frame
+    fn level_gravity(&self) -> Option<f32> {
+        match &self.mode {
+            Mode::Marathon { gravity, .. } => Some(gravity.rows_per_frame(self.level()?)),
+            Mode::Master { level } => Some(gravity::table_rows(gravity::MASTER_GRAVITY, *level)),
+            _ => None,
+        }
+    }
+
+    fn has_gravity(&self) -> bool {
+        self.level_gravity().map_or(self.config.gravity.is_some(), |rows| rows > 0.0)
+    }
+
+    /// At 20G pieces are always on the ground, even right after spawning or sliding off a ledge
+    pub fn is_20g(&self) -> bool {
+        self.level_gravity().is_some_and(|rows| rows >= (gravity::MAX_GRAVITY / gravity::G) as f32)
+    }
+
+    /// The configured delays, or the ones for the current level in modes that change them
+    fn timings(&self) -> Timings {
+        let c = self.config;
+        let configured = Timings {
+            are: c.are,
+            line_clear_delay: c.line_clear_delay,
+            lock_delay: c.lock_delay.0,
+        };
+        match (&self.mode, self.level()) {
+            (Mode::Marathon { lock_delay, .. }, Some(level)) => Timings {
+                lock_delay: gravity::step(lock_delay, level).unwrap_or(c.lock_delay.0),
+                ..configured
+            },
+            (Mode::Master { .. }, Some(level)) => {
+                gravity::step(gravity::MASTER_TIMINGS, level).unwrap_or(configured)
+            }
+            _ => configured,
+        }
+    }
+
+    /// How long the game has been going as of `now`, not counting the time spent paused
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        match self.start_time {
+            Some(start) => {
+                self.end_time.or(self.paused).unwrap_or(now).saturating_duration_since(start)
+            }
+            None => Duration::ZERO,
+        }
+    }
+
+    /// How much longer a timed game has left as of `now`, the full limit before it starts
+    pub fn time_remaining(&self, now: Instant) -> Option<Duration> {
+        let limit = self.mode.time_limit()?;
+        Some(limit.saturating_sub(self.elapsed(now)))
+    }
+
+    pub fn pending_garbage_lines(&self) -> u16 {
+        self.pending_garbage.iter().map(|&(_, lines)| lines as u16).sum()
+    }
+
     pub fn spin_shortlist(nodes: &[Node]) -> Vec<Node> {
         let mut bounties =
             vec![Piece::I, Piece::J, Piece::L, Piece::T, Piece::Z, Piece::S, Piece::T];
@@ -227,14 +535,42 @@ impl Game {
         SpinFormatter(self)
     }
 
+    /// The search only sees 10 columns and a single hold slot, and always tops out at 20 rows, so
+    /// it's turned off for anything else rather than suggesting placements that can't be made
+    pub fn search_enabled(&self) -> bool {
+        self.mode.search_enabled()
+            && self.width() <= 10
+            && self.config.height == 20
+            && self.hold_slots() <= 1
+    }
+
+    /// Whether a suggestion placing `pieces` in order can be followed. The search always thinks
+    /// it can hold, so with hold turned off the pieces have to come straight from the queue
+    pub fn can_follow(&self, pieces: impl IntoIterator<Item = tetrizz::data::Piece>) -> bool {
+        let queue = std::iter::once(self.current.piece).chain(self.upcomming.iter().copied());
+        self.hold_slots() > 0 || pieces.into_iter().zip(queue).all(|(a, b)| a == b.into())
+    }
+
     pub fn draw_only_mino(&self) -> bool {
         match self.mode {
-            Mode::Sprint { .. } => false,
+            Mode::Sprint { .. }
+            | Mode::Cheese { .. }
+            | Mode::Marathon { .. }
+            | Mode::Master { .. }
+            | Mode::Ultra { .. } => false,
             Mode::TrainingLab { mino_mode, .. } => mino_mode,
         }
     }
 
+    /// Sprint hides everything while paused so pausing can't be used to plan ahead
+    fn hidden_by_pause(&self) -> bool {
+        self.paused.is_some() && matches!(self.mode, Mode::Sprint { .. })
+    }
+
     pub fn should_draw_board(&self) -> bool {
+        if self.hidden_by_pause() {
+            return false;
+        }
         match &self.mode {
             Mode::TrainingLab { lookahead: Some(lookahead), .. } => lookahead.board_visible,
             _ => true,
@@ -242,6 +578,9 @@ impl Game {
     }
 
     pub fn should_draw_hold(&self) -> bool {
+        if self.hidden_by_pause() {
+            return false;
+        }
         match &self.mode {
             Mode::TrainingLab { lookahead: Some(lookahead), .. } => lookahead.board_visible,
             _ => true,
@@ -249,6 +588,9 @@ impl Game {
     }
 
     pub fn should_draw_queue(&self) -> bool {
+        if self.hidden_by_pause() {
+            return false;
+        }
         match &self.mode {
             Mode::TrainingLab { lookahead: Some(lookahead), .. } => lookahead.board_visible,
             _ => true,
@@ -261,46 +603,93 @@ impl Game {
         Self {
             config,
             rng: StdRng::from_os_rng(),
-            board: [[Cell::Empty; 10]; 50],
+            randomizer: config.randomizer.build(),
+            board: Board::default(),
             upcomming: Default::default(),
             current: PieceLocation::new(Piece::I, (3, 21), Rotation::North),
-            hold: None,
+            hold: Vec::new(),
             lines: 0,
+            garbage_cleared: 0,
             pieces: 0,
-            mode: Mode::Sprint { target_lines: 40 },
+            combo: 0,
+            b2b: 0,
+            attack: 0,
+            score: 0,
+            mode: Mode::default(),
             timers: Default::default(),
             started_right: None,
             started_left: None,
             time: Instant::now(),
             start_time: None,
             end_time: None,
+            paused: None,
             soft_dropping: false,
             can_hold: true,
-            spin: false,
-            state: GameState::Done,
+            last_placement: None,
+            last_kick: None,
+            fall: 0.0,
+            state: GameState::Done(GameResult {
+                outcome: Outcome::Aborted,
+                stats: Stats::default(),
+            }),
+            clearing: Vec::new(),
+            buffered: Vec::new(),
+            pending_garbage: VecDeque::new(),
+            garbage_rng: StdRng::from_os_rng(),
             spins: Default::default(),
             solution: None,
             history: VecDeque::new(),
+            redo: Vec::new(),
         }
     }
 
     pub fn start(&mut self, seed: Option<u64>, sound: &SoundPlayer<impl Sink>) {
+        self.start_at(seed, Instant::now(), sound);
+    }
+
+    /// [`Game::start`] at `time` instead of now, for driving the game with made up timestamps
+    pub fn start_at(&mut self, seed: Option<u64>, time: Instant, sound: &SoundPlayer<impl Sink>) {
         self.state = GameState::Startup;
-        self.board = [[Cell::Empty; 10]; 50];
-        self.hold = None;
+        self.board = Board::default();
+        self.hold.clear();
         self.lines = 0;
+        self.garbage_cleared = 0;
         self.pieces = 0;
+        self.combo = 0;
+        self.b2b = 0;
+        self.attack = 0;
+        self.score = 0;
+        self.last_placement = None;
+        self.clearing.clear();
+        self.buffered.clear();
+        self.pending_garbage.clear();
+        self.redo.clear();
         self.upcomming.clear();
-        self.rng = StdRng::seed_from_u64(seed.unwrap_or_else(|| {
-            SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis() as u64
-        }));
-        self.fill_bag();
-        self.time = Instant::now();
+        let seed = seed.unwrap_or_else(|| {
+            let millis =
+                SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis()
+                    as u64;
+            match self.config.randomizer {
+                // jstris only takes seeds typed into its room settings
+                RandomizerKind::Jstris => random_seed(&mut StdRng::seed_from_u64(millis)),
+                _ => millis,
+            }
+        });
+        self.rng = StdRng::seed_from_u64(seed);
+        self.garbage_rng = StdRng::seed_from_u64(!seed);
+        self.randomizer = self.config.randomizer.build();
+        self.randomizer.seed(seed);
+        self.refill_cheese();
+        self.fill_queue();
+        self.time = time;
         self.start_time = None;
-        if matches!(self.mode, Mode::Sprint { .. }) {
+        self.paused = None;
+        // jstris never rerolls, and matching its sequence is the point of using its randomizer
+        let no_sz_start = self.config.no_sz_start.unwrap_or(self.mode.no_sz_start());
+        if no_sz_start && self.config.randomizer != RandomizerKind::Jstris {
             while let Some(Piece::Z | Piece::S) = self.upcomming.front() {
                 self.upcomming.clear();
-                self.fill_bag();
+                self.fill_queue();
             }
         }
         self.mode.start();
@@ -311,26 +700,84 @@ impl Game {
         self.set_timer(TimerEvent::Start);
     }
 
-    pub fn handle(&mut self, event: Event, time: Instant, sound: &SoundPlayer<impl Sink>) -> bool {
+    /// Whether the frontend should pass this input on, which is only movement during the
+    /// countdown so DAS can be charged before the first piece
+    pub fn accepts(&self, input: InputEvent) -> bool {
+        use {GameState::*, InputEvent::*};
+        match self.state {
+            Running | Are => true,
+            Startup => matches!(input, PressLeft | PressRight | ReleaseLeft | ReleaseRight),
+            Done(_) => false,
+        }
+    }
+
+    /// Fires every timer that's due by `until` at the time it was set for, which keeps the game
+    /// the same no matter how late this gets called. Returns the placements this caused
+    pub fn advance(
+        &mut self,
+        until: Instant,
+        sound: &SoundPlayer<impl Sink>,
+    ) -> Vec<PlacementResult> {
+        let mut placed = vec![];
+        while self.paused.is_none()
+            && let Some(&(t, timer_event)) = self.timers.front()
+            && t <= until
+        {
+            self.timers.pop_front();
+            placed.extend(self.handle(Event::Timer(timer_event), t, sound));
+        }
+        placed
+    }
+
+    /// Returns the result of the placement if this event locked a piece
+    pub fn handle(
+        &mut self,
+        event: Event,
+        time: Instant,
+        sound: &SoundPlayer<impl Sink>,
+    ) -> Option<PlacementResult> {
         use {Event::*, TimerEvent::*};
         let ret = self._handle(event, time, sound);
-        if let Input(kind) = event {
-            if let Mode::TrainingLab { lookahead: Some(lookahead), .. } = &mut self.mode {
-                if lookahead.board_visible && kind != InputEvent::Undo {
-                    lookahead.board_visible = false;
-                } else if self.pieces >= lookahead.next_piece_goal {
-                    self.clear_timer(Lookahead);
-                    self.set_timer(Lookahead);
-                }
+        if let Input(kind) = event
+            && kind != InputEvent::Pause
+            && self.paused.is_none()
+            && let Mode::TrainingLab { lookahead: Some(lookahead), .. } = &mut self.mode
+        {
+            if lookahead.board_visible && !matches!(kind, InputEvent::Undo | InputEvent::Redo) {
+                lookahead.board_visible = false;
+            } else if self.pieces >= lookahead.next_piece_goal {
+                self.clear_timer(Lookahead);
+                self.set_timer(Lookahead);
             }
         }
         ret
     }
 
-    pub fn _handle(&mut self, event: Event, time: Instant, sound: &SoundPlayer<impl Sink>) -> bool {
+    pub fn _handle(
+        &mut self,
+        event: Event,
+        time: Instant,
+        sound: &SoundPlayer<impl Sink>,
+    ) -> Option<PlacementResult> {
         use {Event::*, GameState::*, InputEvent::*, TimerEvent::*};
         self.time = time;
         debug!("handling event: {event:?}");
+        if let Some(paused) = self.paused {
+            if event == Input(Pause) {
+                self.resume(paused);
+            }
+            return None;
+        }
+        if self.state == GameState::Are {
+            match event {
+                Input(input @ (Cw | Ccw | Flip | Hold)) => {
+                    self.buffered.push(input);
+                    return None;
+                }
+                Input(Hard) => return None,
+                _ => {}
+            }
+        }
         match event {
             Input(PressLeft) => {
                 if self.state == Running && self.try_move((-1, 0)) {
@@ -360,7 +807,10 @@ impl Game {
                     self.clear_timer(Arr);
                 }
                 self.started_left = None;
-                self.handle_das();
+                if self.state == Running && self.handle_das() {
+                    sound.play(Action::Move).ok();
+                }
+                self.resume_das(DasRight, self.started_right);
             }
             Input(ReleaseRight) => {
                 self.clear_timer(DasRight);
@@ -372,18 +822,18 @@ impl Game {
                     self.clear_timer(Arr);
                 }
                 self.started_right = None;
-                self.handle_das();
+                if self.state == Running && self.handle_das() {
+                    sound.play(Action::Move).ok();
+                }
+                self.resume_das(DasLeft, self.started_left);
             }
             Input(Hold) => {
                 if self.can_hold {
                     if !self.hold() {
-                        sound.play(Meta::Lose).ok();
-                        self.state = Done;
-                        self.end_time = Some(self.time);
-                        self.timers.clear();
+                        self.lose(TopOut::BlockOut, sound);
                     } else {
                         sound.play(Action::Hold).ok();
-                        self.can_hold = false;
+                        self.can_hold = self.config.hold == HoldRule::Unlimited;
                     }
                 } else {
                     sound.play(Action::NoHold).ok();
@@ -391,39 +841,47 @@ impl Game {
             }
             Input(Undo) => {
                 if !self.mode.allows_undo() {
-                    return false;
+                    return None;
                 }
-                let Some(prev) = self.history.pop_back() else {
-                    return false;
-                };
-                self.board = prev.board;
-                assert!(
-                    self.spawn(prev.current.piece),
-                    "shouldn't be invalid since that piece was able to be placed"
-                );
-                self.hold = prev.hold;
-                self.upcomming = prev.upcomming;
-                self.pieces = prev.pieces_placed;
-                self.spins = prev.spins;
-                if let Mode::TrainingLab { lookahead: Some(lookahead), .. } = &mut self.mode {
-                    lookahead.board_visible = true;
-                    lookahead.next_piece_goal = self.pieces + lookahead.min_placements;
+                let prev = self.history.pop_back()?;
+                self.redo.push(self.moment());
+                self.restore(prev);
+            }
+            Input(Redo) => {
+                if !self.mode.allows_undo() {
+                    return None;
                 }
+                let next = self.redo.pop()?;
+                self.push_history(self.moment());
+                self.restore(next);
             }
             Input(Hard) | Timer(Lock | Extended | Timeout) => {
-                self.hard_drop(sound);
-                // at 536 bytes per Moment, we store 200 moves (107.2kB) max
-                if self.history.len() > 200 {
-                    self.history.pop_front();
-                }
-                return true;
+                return Some(self.hard_drop(sound));
             }
             Timer(t @ (SoftDrop | Gravity)) => {
                 if self.state == Running {
+                    let mut dropped = 0;
                     if self.config.soft_drop == 0 && t == SoftDrop {
-                        while self.try_drop() {}
+                        while self.try_drop() {
+                            dropped += 1;
+                        }
                     } else {
-                        self.try_drop();
+                        // fast enough gravity moves multiple rows per frame
+                        let rows = match (t, self.level_gravity()) {
+                            (Gravity, Some(rows)) if rows >= 1.0 => {
+                                self.fall += rows;
+                                let whole = self.fall.floor();
+                                self.fall -= whole;
+                                whole as u32
+                            }
+                            _ => 1,
+                        };
+                        while dropped < rows && self.try_drop() {
+                            dropped += 1;
+                        }
+                    }
+                    if t == SoftDrop {
+                        self.score += score::SOFT_DROP * dropped;
                     }
                 }
                 self.set_timer(t);
@@ -433,11 +891,23 @@ impl Game {
                 let next = self.pop_piece();
                 self.spawn(next);
                 self.start_time = Some(time);
+                if let Some(limit) = self.mode.time_limit() {
+                    self.set_timer_at(time + limit, End);
+                }
                 sound.play(Meta::Go).ok();
             }
+            Timer(End) => {
+                sound.play(Meta::Win).or_else(|_| sound.play(Clear::Single)).ok();
+                self.finish(Outcome::Cleared);
+            }
             Input(rot @ (Cw | Ccw | Flip)) => {
                 if self.try_rotate(rot.try_into().expect("should always be a rotation")) {
-                    sound.play(Action::Rotate).ok();
+                    let effect = match self.detect_spin() {
+                        SpinKind::None => Action::Rotate,
+                        SpinKind::Mini => Action::MiniSpin,
+                        SpinKind::Full => Action::Spin,
+                    };
+                    sound.play(effect).ok();
                 }
                 // confirmed: jstris resets it even if you don't successfully rotate
                 self.clear_timer(Lock);
@@ -445,15 +915,14 @@ impl Game {
             }
             Input(PressSoft) => {
                 self.soft_dropping = true;
-                self.clear_timer(Gravity);
-                self.set_timer(SoftDrop);
-            }
-            Input(ReleaseSoft) => {
-                self.soft_dropping = false;
-                self.clear_timer(SoftDrop);
-                self.set_timer(Gravity);
+                if self.fall_timer() == SoftDrop {
+                    self.clear_timer(Gravity);
+                    self.set_timer(SoftDrop);
+                }
             }
+            Input(ReleaseSoft) => self.stop_soft_drop(),
             Input(Restart | Quit) => unreachable!("should be handled in outer event loop"),
+            Input(Pause) => self.pause(),
 
             // TODO: add das sound effect
             Input(ShowSolution(ind)) => match ind {
@@ -464,6 +933,8 @@ impl Game {
                     let suggestion = &Game::spin_shortlist(&self.spins)[ind as usize - 1];
                     let mut game = self.clone();
                     game.config.ghost = false;
+                    game.config.are = 0;
+                    game.config.line_clear_delay = 0;
                     for (m, _) in &suggestion.moves {
                         info!("{:?} x:{} y:{} {:?} spin:{}", m.piece, m.x, m.y, m.rotation, m.spun);
                     }
@@ -471,15 +942,56 @@ impl Game {
                 }
             },
             Timer(DasLeft | DasRight) => {
-                if self.state == Running {
-                    self.handle_das()
+                if self.state == Running && self.handle_das() {
+                    sound.play(Action::Move).ok();
                 }
             }
             Timer(Arr) => {
-                todo!()
+                if self.state == Running
+                    && let Some(dir) = self.das_direction()
+                {
+                    if self.try_move((dir, 0)) {
+                        sound.play(Action::Move).ok();
+                    }
+                    self.set_timer(Arr);
+                }
+            }
+            Timer(TimerEvent::Are) => {
+                self.clear_lines();
+                let overflows = self.garbage_overflows();
+                // the piece that just locked is still `current`, so there's nothing to displace
+                if self.raise_garbage() {
+                    sound.play(Meta::Garbage).ok();
+                }
+                if overflows {
+                    self.lose(TopOut::GarbageOut, sound);
+                    return None;
+                }
+                self.state = Running;
+                if !self.spawn_next() {
+                    self.lose(TopOut::BlockOut, sound);
+                    return None;
+                }
+                for input in std::mem::take(&mut self.buffered) {
+                    self._handle(Input(input), time, sound);
+                }
+            }
+            Input(InputEvent::Garbage(lines)) => {
+                let ready = time + FRAME * self.config.garbage_delay as u32;
+                self.pending_garbage.push_back((ready, lines));
+                self.set_timer_at(ready, TimerEvent::Garbage);
             }
-            Timer(Are) => {
-                todo!()
+            // during ARE this waits for the line clear so the rows being cleared don't move
+            Timer(TimerEvent::Garbage) => {
+                if self.state == Running {
+                    let overflows = self.garbage_overflows();
+                    if self.raise_garbage() {
+                        sound.play(Meta::Garbage).ok();
+                        if overflows || !self.displace_current() {
+                            self.lose(TopOut::GarbageOut, sound);
+                        }
+                    }
+                }
             }
             Timer(Lookahead) => {
                 if let Mode::TrainingLab { lookahead: Some(lookahead), .. } = &mut self.mode {
@@ -489,21 +1001,11 @@ impl Game {
             }
         };
         // TODO: set lock timers if on the ground and they arent already set
-        false
+        None
     }
 
     pub fn ghost_pos(&self) -> PieceLocation {
-        let current_pos = self.current.blocks();
-        let mut ghost = current_pos;
-        let mut y = self.current.pos.1;
-        loop {
-            let next = ghost.map(|(x, y)| (x, y - 1));
-            if !self.check_valid(next) {
-                break;
-            }
-            y -= 1;
-            ghost = next;
-        }
+        let y = self.current.pos.1 - self.board.drop_distance(self.current.blocks());
         let pos = (self.current.pos.0, y);
         let rot = self.current.rot;
         let piece = self.current.piece;
@@ -517,23 +1019,39 @@ impl Game {
             DasLeft | DasRight => c.das,
             Arr => c.arr,
             SoftDrop => c.soft_drop,
-            Gravity => {
-                let Some(gravity) = c.gravity else { return };
-                gravity
-            }
-            Lock => c.lock_delay.0,
+            Gravity => match self.level_gravity() {
+                Some(rows) if rows > 0.0 => {
+                    let frames = 1.0 / rows.min(1.0) as f64;
+                    self.set_timer_at(self.time + FRAME.mul_f64(frames), t);
+                    return;
+                }
+                Some(_) => return,
+                None => {
+                    let Some(gravity) = c.gravity else { return };
+                    gravity
+                }
+            },
+            Lock => self.timings().lock_delay,
             Extended => c.lock_delay.1,
             Timeout => {
-                if c.gravity.is_none() {
+                if !self.has_gravity() {
                     return;
                 };
                 c.lock_delay.2
             }
             Start => 60,
-            Are => todo!(),
+            Are => {
+                let Timings { are, line_clear_delay, .. } = self.timings();
+                are + if self.clearing.is_empty() { 0 } else { line_clear_delay }
+            }
             Lookahead => self.mode.lookahead_timeout(),
+            Garbage => c.garbage_delay,
+            End => unreachable!("set from the mode's time limit when the game starts"),
         };
-        let time = self.time + FRAME * frames as u32;
+        self.set_timer_at(self.time + FRAME * frames as u32, t);
+    }
+
+    fn set_timer_at(&mut self, time: Instant, t: TimerEvent) {
         let idx = self.timers.partition_point(|&(i, _)| i < time);
         self.timers.insert(idx, (time, t))
     }
@@ -542,119 +1060,442 @@ impl Game {
         self.timers.retain(|&(_, ev)| ev != t)
     }
 
+    fn has_timer(&self, t: TimerEvent) -> bool {
+        self.timers.iter().any(|&(_, ev)| ev == t)
+    }
+
+    /// Saves the current state before a placement or hold so it can be undone, which also means
+    /// anything that was undone can't be redone anymore
     fn push_moment(&mut self) {
-        let moment = Moment {
+        self.push_history(self.moment());
+        self.redo.clear();
+    }
+
+    fn push_history(&mut self, moment: Moment) {
+        // at 1088 bytes per Moment plus its spins, we store 200 moves (~218kB) max
+        if self.history.len() == 200 {
+            self.history.pop_front();
+        }
+        self.history.push_back(moment);
+    }
+
+    fn moment(&self) -> Moment {
+        Moment {
             board: self.board,
             current: self.current,
-            hold: self.hold,
+            hold: self.hold.clone(),
             upcomming: self.upcomming.clone(),
             spins: self.spins.clone(),
             pieces_placed: self.pieces,
-        };
-        self.history.push_back(moment);
+            lines: self.lines,
+            garbage_cleared: self.garbage_cleared,
+            combo: self.combo,
+            b2b: self.b2b,
+            attack: self.attack,
+            score: self.score,
+            pending_garbage: self.pending_garbage.clone(),
+        }
     }
 
-    fn hard_drop(&mut self, sound: &SoundPlayer<impl Sink>) {
-        while self.try_drop() {}
-        let old_lines = self.lines;
+    /// Goes back (or forward) to a moment from undo history, with its piece freshly spawned
+    fn restore(&mut self, moment: Moment) {
+        self.board = moment.board;
+        self.state = GameState::Running;
+        self.last_placement = None;
+        self.clearing.clear();
+        self.buffered.clear();
+        self.clear_timer(TimerEvent::Are);
+        self.clear_timer(TimerEvent::Lookahead);
+        assert!(
+            self.spawn(moment.current.piece),
+            "shouldn't be invalid since that piece was able to be placed"
+        );
+        self.hold = moment.hold;
+        self.upcomming = moment.upcomming;
+        self.pieces = moment.pieces_placed;
+        self.lines = moment.lines;
+        self.garbage_cleared = moment.garbage_cleared;
+        self.combo = moment.combo;
+        self.b2b = moment.b2b;
+        self.attack = moment.attack;
+        self.score = moment.score;
+        self.pending_garbage = moment.pending_garbage;
+        // the timers for garbage that's already risen since are gone, so set them up again
+        self.clear_timer(TimerEvent::Garbage);
+        let ready: Vec<_> = self.pending_garbage.iter().map(|&(ready, _)| ready).collect();
+        for ready in ready {
+            self.set_timer_at(ready, TimerEvent::Garbage);
+        }
+        self.spins = moment.spins;
+        // show the board again and start counting placements from here
+        if let Mode::TrainingLab { lookahead: Some(lookahead), .. } = &mut self.mode {
+            lookahead.board_visible = true;
+            lookahead.next_piece_goal = self.pieces + lookahead.min_placements;
+        }
+    }
+
+    fn hard_drop(&mut self, sound: &SoundPlayer<impl Sink>) -> PlacementResult {
+        while self.try_drop() {
+            self.score += score::HARD_DROP;
+        }
         self.push_moment();
-        // TODO: redo with "piece placement result struct"
-        if self.lock() {
+        let result = self.lock();
+        if let Some(reason) = result.top_out {
+            self.lose(reason, sound);
+        } else {
             // TODO: maybe just play both at the same time?
-            let effect: Sound = match (self.lines - old_lines, self.spin) {
-                (0, _) => Action::Lock.into(), // TODO: differentiate lock/harddrop
-                (1, false) => Clear::Single.into(),
-                (1, true) => Clear::Tspin.into(),
-                (2, false) => Clear::Double.into(),
-                (2, true) => Clear::TspinDouble.into(),
-                (3, false) => Clear::Triple.into(),
-                (3, true) => Clear::TSpinTriple.into(),
-                (4, _) => Clear::Quad.into(),
-                _ => unreachable!("impossible line clear"),
-            };
-            sound.play(effect).ok();
-            if self.mode.is_complete(self.lines) {
+            sound.play(result.sound()).ok();
+            if self.is_complete() {
                 sound.play(Meta::Win).or_else(|_| sound.play(Clear::Single)).ok();
-                self.finish();
+                self.finish(Outcome::Cleared);
             }
+        }
+        result
+    }
+
+    /// Freezes the game until the next [`InputEvent::Pause`]. Held keys count as released since
+    /// releasing them while paused goes unnoticed
+    fn pause(&mut self) {
+        use TimerEvent::*;
+        self.paused = Some(self.time);
+        self.started_left = None;
+        self.started_right = None;
+        self.clear_timer(DasLeft);
+        self.clear_timer(DasRight);
+        self.clear_timer(Arr);
+        self.stop_soft_drop();
+    }
+
+    /// Soft drop only takes over from gravity when it's faster, so it never slows a piece down
+    fn fall_timer(&self) -> TimerEvent {
+        let gravity = match self.level_gravity() {
+            Some(rows) => rows,
+            None => self.config.gravity.map_or(0.0, |frames| 1.0 / frames as f32),
+        };
+        let soft_drop = match self.config.soft_drop {
+            0 => f32::INFINITY,
+            frames => 1.0 / frames as f32,
+        };
+        if self.soft_dropping && soft_drop > gravity {
+            TimerEvent::SoftDrop
         } else {
-            sound.play(Meta::Lose).ok();
-            self.finish();
+            TimerEvent::Gravity
         }
     }
 
-    fn finish(&mut self) {
-        self.state = GameState::Done;
+    fn stop_soft_drop(&mut self) {
+        self.soft_dropping = false;
+        if self.has_timer(TimerEvent::SoftDrop) {
+            self.clear_timer(TimerEvent::SoftDrop);
+            self.set_timer(TimerEvent::Gravity);
+        }
+    }
+
+    /// Picks up where [`Game::pause`] left off by pushing everything that was scheduled back by
+    /// however long the game was paused
+    fn resume(&mut self, paused: Instant) {
+        let length = self.time.saturating_duration_since(paused);
+        for (t, _) in &mut self.timers {
+            *t += length;
+        }
+        for (t, _) in &mut self.pending_garbage {
+            *t += length;
+        }
+        if let Some(start) = &mut self.start_time {
+            *start += length;
+        }
+        self.paused = None;
+    }
+
+    /// Ends the game early, for when it gets restarted or quit
+    pub fn abort(&mut self, time: Instant) {
+        if self.state.outcome().is_none() {
+            // time spent paused doesn't count
+            self.time = self.paused.unwrap_or(time);
+            self.finish(Outcome::Aborted);
+        }
+    }
+
+    fn lose(&mut self, reason: TopOut, sound: &SoundPlayer<impl Sink>) {
+        sound.play(Meta::Lose).ok();
+        self.finish(Outcome::ToppedOut(reason));
+    }
+
+    fn finish(&mut self, outcome: Outcome) {
+        self.clear_lines();
         self.end_time = Some(self.time);
         self.timers.clear();
+        let stats = Stats {
+            time: self.elapsed(self.time),
+            pieces: self.pieces,
+            lines: self.lines,
+            attack: self.attack,
+            score: self.score,
+        };
+        self.state = GameState::Done(GameResult { outcome, stats });
+    }
+
+    /// Tops up the queue so there's always a full preview, however few pieces the randomizer
+    /// hands out at a time
+    fn fill_queue(&mut self) {
+        while self.upcomming.len() < MAX_PREVIEW as usize {
+            let pieces = self.randomizer.next_batch(&mut self.rng);
+            self.upcomming.extend(pieces);
+        }
+    }
+
+    /// How many upcoming pieces are shown
+    pub fn preview(&self) -> usize {
+        self.config.preview.min(MAX_PREVIEW) as usize
+    }
+
+    fn width(&self) -> usize {
+        self.config.width as usize
     }
 
-    fn fill_bag(&mut self) -> &mut Self {
-        use Piece::*;
-        let mut pieces = [I, J, L, O, S, T, Z];
-        pieces.shuffle(&mut self.rng);
-        self.upcomming.extend(pieces);
-        self
+    /// The visible height plus the buffer above it
+    fn rows(&self) -> usize {
+        self.config.height as usize + BUFFER
     }
 
     pub fn check_valid(&self, pos: Pos) -> bool {
-        pos.into_iter().all(|(x, y)| {
-            (0..10).contains(&x)
-                && (0..30).contains(&y)
-                && self.board[y as usize][x as usize] == Cell::Empty
-        })
+        self.board.fits(pos, self.width(), self.rows())
     }
 
-    pub fn lock(&mut self) -> bool {
+    pub fn lock(&mut self) -> PlacementResult {
+        let spin = self.detect_spin();
+        // clears score at the level they were made on
+        let level = match self.mode {
+            Mode::Marathon { .. } => self.level().unwrap_or(1),
+            _ => 1,
+        };
         info!("pos: {:?}", self.current.pos);
         for (x, y) in self.current.blocks() {
             info!("{x} {y}");
-            self.board[y as usize][x as usize] = Cell::Piece(self.current.piece);
+            self.board.set(x as usize, y as usize, Cell::Piece(self.current.piece));
+        }
+        let full = self.board.full_rows(self.width());
+        self.clearing = (0..self.rows()).filter(|&i| full >> i & 1 == 1).collect();
+        let lock_out = self.lock_out();
+        let perfect_clear = full != 0 && self.board.is_empty_except(full);
+        self.lines += self.clearing.len() as u16;
+        if let Mode::Master { level } = &mut self.mode {
+            *level = (*level + self.clearing.len() as u16).min(MASTER_MAX_LEVEL);
+            // the next piece counts too, except at the end of a section
+            if *level % 100 != 99 && *level < MASTER_MAX_LEVEL - 1 {
+                *level += 1;
+            }
+        }
+        self.garbage_cleared +=
+            self.clearing.iter().filter(|&&i| self.board[i].contains(&Cell::Garbage)).count()
+                as u16;
+        self.pieces += 1;
+        let prev_b2b = self.b2b;
+        if self.clearing.is_empty() {
+            self.combo = 0;
+        } else {
+            self.combo += 1;
+            if spin != SpinKind::None || self.clearing.len() == 4 {
+                self.b2b += 1;
+            } else {
+                self.b2b = 0;
+            }
+        }
+        let mut result = PlacementResult {
+            location: self.current,
+            rows: self.clearing.clone(),
+            spin,
+            perfect_clear,
+            combo: self.combo,
+            b2b: self.b2b,
+            attack: 0,
+            cancelled: 0,
+            score: 0,
+            top_out: None,
+        };
+        result.attack = self.config.attack_table.attack(&result, prev_b2b);
+        result.score = score::score(&result) * level as u32;
+        self.score += result.score;
+        result.cancelled = self.cancel_garbage(result.attack);
+        self.attack += (result.attack - result.cancelled) as u32;
+        let Timings { are, line_clear_delay, .. } = self.timings();
+        if lock_out.is_some() {
+            result.top_out = lock_out;
+        } else if are == 0 && (self.clearing.is_empty() || line_clear_delay == 0) {
+            self.clear_lines();
+            if !self.spawn_next() {
+                result.top_out = Some(TopOut::BlockOut);
+            }
+        } else {
+            use TimerEvent::*;
+            for t in [SoftDrop, Gravity, Lock, Extended, Timeout] {
+                self.clear_timer(t);
+            }
+            self.state = GameState::Are;
+            self.set_timer(Are);
         }
-        for i in (0..23).rev() {
-            if self.board[i].iter().all(|c| matches!(c, Cell::Piece(_))) {
-                for j in i..22 {
-                    self.board[j] = self.board[j + 1];
+        self.last_placement = Some(result.clone());
+        result
+    }
+
+    /// Whether the piece that's locking tops out under [`Config::lock_out`], going by the rows
+    /// its blocks end up in once the lines being cleared are gone
+    fn lock_out(&self) -> Option<TopOut> {
+        let height = self.config.height as usize;
+        let rows: Vec<_> = (self.current.blocks().into_iter())
+            .map(|(_, y)| y as usize)
+            .filter(|y| !self.clearing.contains(y))
+            .map(|y| y - self.clearing.iter().filter(|&&cleared| cleared < y).count())
+            .collect();
+        let above = rows.iter().filter(|&&y| y >= height).count();
+        if above == 0 {
+            return None;
+        }
+        match self.config.lock_out {
+            LockOut::None => None,
+            _ if above == rows.len() => Some(TopOut::LockOut),
+            LockOut::Full => None,
+            LockOut::Partial => Some(TopOut::PartialLockOut),
+        }
+    }
+
+    fn clear_lines(&mut self) {
+        // top down so the indices of the rows below stay valid
+        for &i in self.clearing.iter().rev() {
+            self.board.clear_row(i);
+        }
+        if !self.clearing.is_empty() {
+            self.refill_cheese();
+        }
+        self.clearing.clear();
+    }
+
+    /// Tops the cheese back up to its height, without adding more rows than are left to dig
+    fn refill_cheese(&mut self) {
+        let Mode::Cheese { target_lines, height } = self.mode else { return };
+        let on_board = self.board.iter().filter(|row| row.contains(&Cell::Garbage)).count() as u16;
+        let left = target_lines.saturating_sub(self.garbage_cleared + on_board);
+        for _ in 0..(height as u16).saturating_sub(on_board).min(left) {
+            // never put a hole right below another one
+            let width = self.width();
+            let hole = match self.board[0][..width].iter().position(|&c| c == Cell::Empty) {
+                Some(above) if self.board[0].contains(&Cell::Garbage) => {
+                    (above + self.garbage_rng.random_range(1..width)) % width
                 }
-                self.lines += 1;
+                _ => self.garbage_rng.random_range(0..width),
+            };
+            self.insert_garbage_rows(1, hole);
+        }
+    }
+
+    /// Uses outgoing attack to cancel pending garbage, oldest first. Returns how much was cancelled
+    fn cancel_garbage(&mut self, attack: u16) -> u16 {
+        let mut remaining = attack;
+        while remaining > 0
+            && let Some((_, lines)) = self.pending_garbage.front_mut()
+        {
+            let cancelled = remaining.min(*lines as u16);
+            *lines -= cancelled as u8;
+            remaining -= cancelled;
+            if *lines == 0 {
+                self.pending_garbage.pop_front();
             }
         }
+        attack - remaining
+    }
+
+    /// Adds all the garbage whose delay is over to the bottom of the board, with one hole column
+    /// per attack. Returns whether any rose
+    fn raise_garbage(&mut self) -> bool {
+        let ready = self.pending_garbage.iter().take_while(|&&(t, _)| t <= self.time).count();
+        let chunks: Vec<_> = self.pending_garbage.drain(..ready).collect();
+        for &(_, lines) in &chunks {
+            let hole = self.garbage_rng.random_range(0..self.width());
+            self.insert_garbage_rows(lines as usize, hole);
+        }
+        !chunks.is_empty()
+    }
+
+    /// Whether raising the garbage that's ready would push the stack out of the top of the board
+    fn garbage_overflows(&self) -> bool {
+        let ready = self.pending_garbage.iter().take_while(|&&(t, _)| t <= self.time);
+        let lines: usize = ready.map(|&(_, lines)| lines as usize).sum();
+        let stack = (0..self.width())
+            .map(|x| (u64::BITS - self.board.column(x).leading_zeros()) as usize)
+            .max()
+            .unwrap_or(0);
+        stack + lines > self.rows()
+    }
+
+    /// Pushes the board up and fills the bottom with garbage rows, all with the same hole
+    fn insert_garbage_rows(&mut self, lines: usize, hole: usize) {
+        let mut row = [Cell::Empty; MAX_WIDTH];
+        row[..self.width()].fill(Cell::Garbage);
+        row[hole] = Cell::Empty;
+        self.board.insert_rows(lines, row, self.rows());
+    }
+
+    /// Pushes the current piece up until it's out of the stack, returns false if it can't be
+    fn displace_current(&mut self) -> bool {
+        let (x, y) = self.current.pos;
+        let Some(dy) = (0..self.rows() as i8)
+            .find(|&dy| self.check_valid(self.current.blocks().map(|(bx, by)| (bx, by + dy))))
+        else {
+            return false;
+        };
+        self.current.pos = (x, y + dy);
+        true
+    }
+
+    fn spawn_next(&mut self) -> bool {
         let next = self.pop_piece();
-        self.pieces += 1;
         self.spawn(next)
     }
 
     fn pop_piece(&mut self) -> Piece {
         let next = self.upcomming.dequeue().unwrap();
-        if self.upcomming.len() < 7 {
-            self.fill_bag();
-        }
+        self.fill_queue();
         next
     }
 
-    fn das_helper(&mut self, dir: i8) {
+    /// The direction of the most recently pressed key, if it's been held long enough to charge DAS
+    fn das_direction(&self) -> Option<i8> {
+        let charged = |start: Instant| self.time - start >= FRAME * self.config.das as u32;
+        match (self.started_left, self.started_right) {
+            (Some(l), Some(r)) if r < l => charged(l).then_some(-1),
+            (Some(l), Some(r)) if l < r => charged(r).then_some(1),
+            (None, Some(r)) => charged(r).then_some(1),
+            (Some(l), None) => charged(l).then_some(-1),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the piece moved so the caller can play a sound
+    fn handle_das(&mut self) -> bool {
+        let Some(dir) = self.das_direction() else { return false };
         if self.config.arr == 0 {
-            while self.try_move((dir, 0)) {}
+            let mut moved = false;
+            while self.try_move((dir, 0)) {
+                moved = true;
+            }
+            moved
+        } else if !self.has_timer(TimerEvent::Arr) {
+            // the first shift happens as soon as DAS charges, then repeats every `arr` frames
+            self.set_timer(TimerEvent::Arr);
+            self.try_move((dir, 0))
         } else {
-            self.set_timer(TimerEvent::Arr)
+            false
         }
     }
 
-    // TODO: return bool for whether it moved to trigger sound
-    fn handle_das(&mut self) {
-        let t = self.time;
-        let threshold = FRAME * self.config.das as u32;
-        match (self.started_left, self.started_right) {
-            (Some(l), Some(r)) => {
-                if r < l && t - l > threshold {
-                    self.das_helper(-1)
-                } else if l < r && t - r > FRAME * self.config.das as u32 {
-                    self.das_helper(1)
-                }
+    /// When one direction is released while the other is still held, the other direction's DAS
+    /// timer (which got cleared when it was overridden) needs to pick back up where it left off
+    fn resume_das(&mut self, timer: TimerEvent, started: Option<Instant>) {
+        if let Some(start) = started {
+            let charged_at = start + FRAME * self.config.das as u32;
+            if charged_at > self.time && !self.has_timer(timer) {
+                self.set_timer_at(charged_at, timer);
             }
-            (None, Some(r)) if t - r > threshold => self.das_helper(1),
-            (Some(l), None) if t - l > threshold => self.das_helper(-1),
-            _ => {}
         }
     }
 
@@ -667,51 +1508,88 @@ impl Game {
             self.clear_timer(Extended);
             self.clear_timer(Timeout);
         }
-        self.can_hold = true;
-        let pos = (4, 21);
-        let rotation = Rotation::North;
+        self.can_hold = self.config.hold != HoldRule::None;
+        // left of center on even widths, same as the guideline
+        let system = self.config.rotation_system;
+        let rotation = system.spawn_rotation(next);
+        let (dx, dy) = system.offset(next, rotation);
+        let pos = ((self.config.width as i8 - 1) / 2 + dx, self.config.height as i8 + 1 + dy);
         let next = PieceLocation::new(next, pos, rotation);
         if !self.check_valid(next.blocks()) {
             return false;
         }
         self.current = next;
-        self.try_drop();
-        self.set_timer(if self.soft_dropping { TimerEvent::SoftDrop } else { TimerEvent::Gravity });
+        self.last_kick = None;
+        self.fall = 0.0;
+        if self.is_20g() {
+            while self.try_drop() {}
+        } else {
+            self.try_drop();
+        }
+        self.set_timer(self.fall_timer());
         self.set_timer(TimerEvent::Timeout);
         true
     }
 
+    /// Puts the current piece in hold and takes out the oldest held piece once all the slots are
+    /// full, or the next piece until then
     pub fn hold(&mut self) -> bool {
         self.push_moment();
-        let piece = if let Some(p) = self.hold {
-            self.hold = Some(self.current.piece);
-            p
+        self.hold.push(self.current.piece);
+        let piece = if self.hold.len() > self.hold_slots() {
+            self.hold.remove(0)
         } else {
-            self.hold = Some(self.current.piece);
             self.pop_piece()
         };
         self.spawn(piece)
     }
 
+    /// How many pieces can be held, none when hold is turned off
+    pub fn hold_slots(&self) -> usize {
+        match self.config.hold {
+            HoldRule::None => 0,
+            _ => self.config.hold_slots.clamp(1, MAX_HOLD_SLOTS) as usize,
+        }
+    }
+
     fn try_rotate(&mut self, dir: Spin) -> bool {
         let PieceLocation { piece, pos, rot } = self.current;
+        let system = self.config.rotation_system;
         let new_rot = rot.rotate(dir);
+        let (from, to) = (system.offset(piece, rot), system.offset(piece, new_rot));
+        let pos = (pos.0 + to.0 - from.0, pos.1 + to.1 - from.1);
         let new_current = PieceLocation::new(piece, pos, new_rot);
         let new_pos = new_current.blocks();
-        let kicks = piece.get_your_kicks(rot, dir);
-        for (dx, dy) in kicks {
+        let mut kicks = match dir {
+            Spin::Flip if system.has_flip_kicks() => match self.config.flip_kicks.kicks(piece, rot)
+            {
+                Some(kicks) => kicks,
+                None => return false,
+            },
+            _ => system.kicks(piece, rot, dir),
+        };
+        if !self.check_valid(new_pos) && !system.can_kick(new_current, |p| self.is_filled(p)) {
+            kicks = &kicks[..1];
+        }
+        for (i, &(dx, dy)) in kicks.iter().enumerate() {
             let displaced = new_pos.map(|(x, y)| (x + dx, y + dy));
             if self.check_valid(displaced) {
                 self.current =
                     PieceLocation::new(self.current.piece, (pos.0 + dx, pos.1 + dy), new_rot);
+                self.last_kick = Some((dir, i));
                 self.handle_das();
+                if self.is_20g() {
+                    while self.try_drop() {}
+                }
                 use TimerEvent::*;
                 if self.can_drop() {
                     self.clear_timer(Lock);
                     self.clear_timer(Extended);
                 } else {
                     self.set_timer(Lock);
-                    self.set_timer(Extended);
+                    if !self.has_timer(Extended) {
+                        self.set_timer(Extended);
+                    }
                 }
                 return true;
             }
@@ -723,6 +1601,48 @@ impl Game {
         false
     }
 
+    fn is_filled(&self, pos: (i8, i8)) -> bool {
+        !self.check_valid([pos; 4])
+    }
+
+    fn detect_spin(&self) -> SpinKind {
+        let Some((dir, kick)) = self.last_kick else { return SpinKind::None };
+        match (self.config.spin_rule, self.current.piece) {
+            (SpinRule::None, _) | (_, Piece::O) => SpinKind::None,
+            (_, Piece::T) => self.detect_t_spin(dir, kick),
+            (SpinRule::AllSpin, _) if self.is_immobile() => SpinKind::Full,
+            (SpinRule::AllMini, _) if self.is_immobile() => SpinKind::Mini,
+            _ => SpinKind::None,
+        }
+    }
+
+    /// tetr.io style all-spin check: the piece can't move left, right, or up
+    fn is_immobile(&self) -> bool {
+        [(-1, 0), (1, 0), (0, 1)]
+            .into_iter()
+            .all(|(dx, dy)| !self.check_valid(self.current.blocks().map(|(x, y)| (x + dx, y + dy))))
+    }
+
+    /// Guideline 3-corner rule: a T that rotated into a spot with 3 of its 4 diagonals filled is
+    /// a spin, and it's a mini unless both corners it's pointing towards are filled (or it used
+    /// the last kick, which covers TSTs and fins)
+    fn detect_t_spin(&self, dir: Spin, kick: usize) -> SpinKind {
+        let (x, y) = self.current.pos;
+        let [front, back] = [[(-1, 1), (1, 1)], [(-1, -1), (1, -1)]].map(|corners| {
+            corners
+                .into_iter()
+                .map(|c| self.current.rot.rotate_block(c))
+                .filter(|&(dx, dy)| self.is_filled((x + dx, y + dy)))
+                .count()
+        });
+        match (front, back) {
+            (2, 1..) => SpinKind::Full,
+            (1, 2) if dir != Spin::Flip && kick == 4 => SpinKind::Full,
+            (1, 2) => SpinKind::Mini,
+            _ => SpinKind::None,
+        }
+    }
+
     fn try_drop(&mut self) -> bool {
         self.try_move((0, -1))
             .then(|| {
@@ -742,13 +1662,19 @@ impl Game {
 
         if self.check_valid(next_current.blocks()) {
             self.current = next_current;
+            self.last_kick = None;
             use TimerEvent::*;
             self.clear_timer(Lock);
             if self.can_drop() {
                 self.clear_timer(Extended);
             } else {
                 self.set_timer(Lock);
-                self.set_timer(Extended);
+                if !self.has_timer(Extended) {
+                    self.set_timer(Extended);
+                }
+            }
+            if dx != 0 && self.is_20g() {
+                while self.try_drop() {}
             }
             true
         } else {
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+/// Gravity is measured in 1/256ths of a row per frame in tables, like tgm does internally
+pub const G: u32 = 256;
+/// fastest gravity there is, the piece lands the same frame it spawns
+pub const MAX_GRAVITY: u32 = 20 * G;
+
+/// How fast pieces fall as the level goes up
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GravityCurve {
+    /// the tetris worlds formula, `(0.8 - (level - 1) * 0.007) ^ (level - 1)` seconds per row
+    #[default]
+    Guideline,
+    /// `(level, gravity)` pairs sorted by level, each speed lasts until the next entry's level
+    Table(Vec<(u16, u32)>),
+}
+
+impl GravityCurve {
+    /// unit: rows per frame
+    pub fn rows_per_frame(&self, level: u16) -> f32 {
+        match self {
+            GravityCurve::Guideline => {
+                let level = level.max(1) as f32 - 1.0;
+                let seconds = (0.8 - level * 0.007).powf(level);
+                (1.0 / (seconds * 60.0)).min((MAX_GRAVITY / G) as f32)
+            }
+            GravityCurve::Table(table) => table_rows(table, level),
+        }
+    }
+}
+
+/// Rows per frame from a table of `(level, gravity)` pairs
+pub fn table_rows(table: &[(u16, u32)], level: u16) -> f32 {
+    step(table, level).unwrap_or(0).min(MAX_GRAVITY) as f32 / G as f32
+}
+
+/// The value of the last entry at or below `level` in a table of `(level, value)` pairs
+pub fn step<T: Copy>(table: &[(u16, T)], level: u16) -> Option<T> {
+    table.iter().take_while(|&&(start, _)| start <= level).last().map(|&(_, value)| value)
+}
+
+/// tgm's master mode gravity by level, dipping back down at 200 and reaching 20G at 500
+pub const MASTER_GRAVITY: &[(u16, u32)] = &[
+    (0, 4),
+    (30, 6),
+    (35, 8),
+    (40, 10),
+    (50, 12),
+    (60, 16),
+    (70, 32),
+    (80, 48),
+    (90, 64),
+    (100, 80),
+    (120, 96),
+    (140, 112),
+    (160, 128),
+    (170, 144),
+    (200, 4),
+    (220, 32),
+    (230, 64),
+    (233, 96),
+    (236, 128),
+    (239, 160),
+    (243, 192),
+    (247, 224),
+    (251, G),
+    (300, 2 * G),
+    (330, 3 * G),
+    (360, 4 * G),
+    (400, 5 * G),
+    (420, 4 * G),
+    (450, 3 * G),
+    (500, MAX_GRAVITY),
+];
+
+/// Delays for one of master mode's sections
+///
+/// unit: frames
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Timings {
+    pub are: u16,
+    /// on top of `are`, like [`Config::line_clear_delay`](crate::Config::line_clear_delay)
+    pub line_clear_delay: u16,
+    pub lock_delay: u16,
+}
+
+const fn timings(are: u16, line_clear_delay: u16, lock_delay: u16) -> Timings {
+    Timings { are, line_clear_delay, lock_delay }
+}
+
+/// tgm2 master's delays by level, which keep getting shorter after gravity maxes out
+pub const MASTER_TIMINGS: &[(u16, Timings)] = &[
+    (0, timings(25, 40, 30)),
+    (500, timings(25, 25, 30)),
+    (600, timings(25, 7, 30)),
+    (700, timings(16, 8, 30)),
+    (800, timings(12, 0, 30)),
+    (900, timings(12, 0, 17)),
+];
@@ -1,17 +1,35 @@
+pub mod attack;
+pub mod board;
+pub mod clock;
 pub mod game;
+pub mod gravity;
+pub mod randomizer;
 pub mod replay;
+pub mod rotation;
+pub mod score;
 pub mod sound;
 #[cfg(test)]
 mod tests;
 
-use std::time::Duration;
+use std::{fmt, time::Duration};
 
 use anyhow::Result;
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
+use strum::EnumString;
 
+pub use attack::AttackTable;
+pub use board::Board;
+pub use clock::FrameClock;
 pub use game::Game;
+pub use game::HoldRule;
+pub use game::LockOut;
 pub use game::Mode;
+pub use game::PlacementResult;
+pub use game::TopOut;
+pub use gravity::GravityCurve;
+pub use randomizer::{Randomizer, RandomizerKind};
+pub use rotation::{FlipKicks, RotationSystem};
 
 pub type Pos = [(i8, i8); 4];
 
@@ -140,6 +158,30 @@ pub enum Spin {
     Flip,
 }
 
+/// Which placements get credited as spins
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum SpinRule {
+    None,
+    /// only T pieces using the 3-corner rule
+    TSpinOnly,
+    /// T pieces use the 3-corner rule, any other piece that can't move after rotating is a spin
+    #[default]
+    AllSpin,
+    /// like `AllSpin`, but non-T spins are only credited as minis
+    AllMini,
+}
+
+/// How a placement was classified by the spin detection rules
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpinKind {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Rotation {
     #[default]
@@ -198,9 +240,11 @@ pub enum InputEvent {
     Quit,
     ShowSolution(u8),
     Undo,
-    // Redo,
-    // Garbage(n) // just for garbage line timer, need special handling to displace current piece upwards
-    // Attack(n)
+    Redo,
+    /// pauses the game, or resumes it if it's already paused
+    Pause,
+    /// receive this many lines of garbage, which rise after the garbage delay unless cancelled
+    Garbage(u8),
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -216,6 +260,9 @@ pub enum TimerEvent {
     Start,
     Are,
     Lookahead,
+    Garbage,
+    /// the time limit ran out
+    End,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -241,7 +288,7 @@ const FRAME: Duration = Duration::from_nanos(16_666_667);
 // TODO: make all these floats (maybe ms instead of frames?)
 // TODO: find jstris softdrop delays and match them
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case", default)]
 pub struct Config {
     pub das: u16,
     pub arr: u16,
@@ -249,6 +296,31 @@ pub struct Config {
     pub soft_drop: u16, // TODO: add support for 0 for instant
     pub lock_delay: (u16, u16, u16),
     pub ghost: bool,
+    /// delay between a piece locking and the next one spawning
+    pub are: u16,
+    /// extra delay on top of `are` when the piece cleared lines
+    pub line_clear_delay: u16,
+    pub spin_rule: SpinRule,
+    pub attack_table: AttackTable,
+    /// delay between receiving garbage and it rising, during which it can be cancelled
+    pub garbage_delay: u16,
+    /// columns on the board, up to [`board::MAX_WIDTH`]
+    pub width: u8,
+    /// visible rows on the board, up to [`board::MAX_HEIGHT`]. Pieces spawn just above these
+    pub height: u8,
+    pub randomizer: RandomizerKind,
+    /// reroll the first bag when it starts with an S or Z, which can't be placed without a hole.
+    /// Left unset, only sprint does
+    pub no_sz_start: Option<bool>,
+    /// the training lab's search always uses SRS+, whatever this is set to
+    pub rotation_system: RotationSystem,
+    pub flip_kicks: FlipKicks,
+    pub lock_out: LockOut,
+    pub hold: HoldRule,
+    /// pieces that can be held at once, up to [`game::MAX_HOLD_SLOTS`]
+    pub hold_slots: u8,
+    /// upcoming pieces shown, up to [`game::MAX_PREVIEW`]
+    pub preview: u8,
 }
 
 impl Default for Config {
@@ -260,6 +332,21 @@ impl Default for Config {
             soft_drop: 4,
             lock_delay: (30, 300, 1200),
             ghost: true,
+            are: 0,
+            line_clear_delay: 0,
+            spin_rule: SpinRule::AllSpin,
+            attack_table: AttackTable::Guideline,
+            garbage_delay: 20,
+            width: 10,
+            height: 20,
+            randomizer: RandomizerKind::Bag7,
+            no_sz_start: None,
+            rotation_system: RotationSystem::SrsPlus,
+            flip_kicks: FlipKicks::Tetrio,
+            lock_out: LockOut::Full,
+            hold: HoldRule::Once,
+            hold_slots: 1,
+            preview: 5,
         }
     }
 }
@@ -268,7 +355,73 @@ impl Default for Config {
 pub enum GameState {
     Startup,
     Running,
-    Done,
+    /// between pieces, rotations and holds get buffered until the next piece spawns
+    Are,
+    Done(GameResult),
+}
+
+impl GameState {
+    /// How the game ended, if it's over
+    pub fn outcome(self) -> Option<Outcome> {
+        match self {
+            GameState::Done(result) => Some(result.outcome),
+            _ => None,
+        }
+    }
+}
+
+/// How a game ended and how it went
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameResult {
+    pub outcome: Outcome,
+    pub stats: Stats,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// the mode's goal was reached, which for timed modes means lasting until the time is up
+    Cleared,
+    ToppedOut(TopOut),
+    /// restarted or quit before the game was over
+    Aborted,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Cleared => f.write_str("cleared"),
+            Outcome::ToppedOut(reason) => reason.fmt(f),
+            Outcome::Aborted => f.write_str("aborted"),
+        }
+    }
+}
+
+/// The numbers a game ended with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// not counting time spent paused
+    pub time: Duration,
+    pub pieces: usize,
+    pub lines: u16,
+    /// garbage that was actually sent, see [`Game::attack`]
+    pub attack: u32,
+    pub score: u32,
+}
+
+impl Stats {
+    /// pieces per second
+    pub fn pps(&self) -> f64 {
+        self.per_second(self.pieces as f64)
+    }
+
+    /// attack per minute
+    pub fn apm(&self) -> f64 {
+        self.per_second(self.attack as f64) * 60.0
+    }
+
+    fn per_second(&self, n: f64) -> f64 {
+        if self.time.is_zero() { 0.0 } else { n / self.time.as_secs_f64() }
+    }
 }
 
 #[derive(Clone, Copy, Default, PartialEq, Eq)]
@@ -298,100 +451,12 @@ impl Rotation {
         }
     }
 }
-// SRS kicks from: https://harddrop.com/wiki/SRS#How_guideline_SRS_actually_works
-// 180 kicks from: https://tetrio.wiki.gg/images/5/52/TETR.IO_180kicks.png?6d5d9d
-// I spins are slightly asymetrical, see https://harddrop.com/wiki/I-spins_in_SRS
-// (yum)
-impl Piece {
-    const fn get_your_kicks(self, rot: Rotation, dir: Spin) -> [(i8, i8); 6] {
-        let next_rot = rot.rotate(dir);
-        match self {
-            Piece::O => [(0, 0); 6], // just be careful not to rotate the O piece at all lol
-            Piece::I => match (rot, next_rot) {
-                (Rotation::East, Rotation::North) => {
-                    [(-1, 0), (-2, 0), (1, 0), (-2, -2), (1, 1), (-1, 0)]
-                }
-                (Rotation::East, Rotation::South) => {
-                    [(0, -1), (-1, -1), (2, -1), (-1, 1), (2, -2), (0, -1)]
-                }
-                (Rotation::East, Rotation::West) => {
-                    [(-1, -1), (0, -1), (-1, -1), (-1, -1), (-1, -1), (-1, -1)]
-                }
-                (Rotation::South, Rotation::North) => {
-                    [(-1, 1), (-1, 0), (-1, 1), (-1, 1), (-1, 1), (-1, 1)]
-                }
-                (Rotation::South, Rotation::East) => {
-                    [(0, 1), (-2, 1), (1, 1), (-2, 2), (1, -1), (0, 1)]
-                }
-                (Rotation::South, Rotation::West) => {
-                    [(-1, 0), (1, 0), (-2, 0), (1, 1), (-2, -2), (-1, 0)]
-                }
-                (Rotation::West, Rotation::North) => {
-                    [(0, 1), (1, 1), (-2, 1), (1, -1), (-2, 2), (0, 1)]
-                }
-                (Rotation::West, Rotation::East) => {
-                    [(1, 1), (0, 1), (1, 1), (1, 1), (1, 1), (1, 1)]
-                }
-                (Rotation::West, Rotation::South) => {
-                    [(1, 0), (2, 0), (-1, 0), (2, 2), (-1, -1), (1, 0)]
-                }
-                (Rotation::North, Rotation::East) => {
-                    [(1, 0), (2, 0), (-1, 0), (-1, -1), (2, 2), (1, 0)]
-                }
-                (Rotation::North, Rotation::West) => {
-                    [(0, -1), (-1, -1), (2, -1), (2, -2), (-1, 1), (0, -1)]
-                }
-                (Rotation::North, Rotation::South) => {
-                    [(1, -1), (1, 0), (1, -1), (1, -1), (1, -1), (1, -1)]
-                }
-                _ => unreachable!(),
-            },
-            _ => match (rot, next_rot) {
-                (Rotation::East, Rotation::North) => {
-                    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2), (0, 0)]
-                }
-                (Rotation::East, Rotation::South) => {
-                    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2), (0, 0)]
-                }
-                (Rotation::East, Rotation::West) => {
-                    [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)]
-                }
-                (Rotation::South, Rotation::North) => {
-                    [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)]
-                }
-                (Rotation::South, Rotation::East) => {
-                    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2), (0, 0)]
-                }
-                (Rotation::South, Rotation::West) => {
-                    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2), (0, 0)]
-                }
-                (Rotation::West, Rotation::North) => {
-                    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2), (0, 0)]
-                }
-                (Rotation::West, Rotation::East) => {
-                    [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)]
-                }
-                (Rotation::West, Rotation::South) => {
-                    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2), (0, 0)]
-                }
-                (Rotation::North, Rotation::East) => {
-                    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2), (0, 0)]
-                }
-                (Rotation::North, Rotation::West) => {
-                    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2), (0, 0)]
-                }
-                (Rotation::North, Rotation::South) => {
-                    [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)]
-                }
-                _ => unreachable!(),
-            },
-        }
-    }
-}
-
 pub const BG_COLOR: (u8, u8, u8) = (20, 20, 20);
-// const DONE_COLOR: (u8, u8, u8) = (106, 106, 106);
-pub const LOST_COLOR: (u8, u8, u8) = (106, 106, 106); // TODO: differentiate from DONE
+pub const DONE_COLOR: (u8, u8, u8) = (80, 160, 110);
+pub const LOST_COLOR: (u8, u8, u8) = (106, 106, 106);
+pub const CLEAR_COLOR: (u8, u8, u8) = (230, 230, 230);
+pub const ATTACK_COLOR: (u8, u8, u8) = (240, 200, 60);
+pub const GARBAGE_COLOR: (u8, u8, u8) = (220, 50, 50);
 
 pub trait Color {
     fn color(self) -> (u8, u8, u8);
@@ -0,0 +1,280 @@
+use rand::{prelude::*, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+use strum::EnumString;
+
+use crate::Piece::{self, *};
+
+const PIECES: [Piece; 7] = [I, J, L, O, S, T, Z];
+
+/// Generates the piece sequence. Pieces come out in batches so bag randomizers can hand over a
+/// whole bag at once, which is also what [`Config::no_sz_start`](crate::Config::no_sz_start)
+/// rerolls
+pub trait Randomizer {
+    /// Called with the game's seed when it starts, for randomizers that bring their own rng
+    fn seed(&mut self, _seed: u64) {}
+
+    /// The next pieces in the sequence, never empty
+    fn next_batch(&mut self, rng: &mut StdRng) -> Vec<Piece>;
+
+    fn boxed_clone(&self) -> Box<dyn Randomizer>;
+}
+
+impl Clone for Box<dyn Randomizer> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+/// Which [`Randomizer`] a game uses
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, EnumString)]
+pub enum RandomizerKind {
+    /// every piece once per bag
+    #[default]
+    #[serde(rename = "7-bag")]
+    #[strum(serialize = "7-bag")]
+    Bag7,
+    /// every piece twice per bag
+    #[serde(rename = "14-bag")]
+    #[strum(serialize = "14-bag")]
+    Bag14,
+    /// a 7-bag with one extra random piece shuffled in
+    #[serde(rename = "7+1-bag")]
+    #[strum(serialize = "7+1-bag")]
+    Bag7Plus1,
+    /// any piece at any time
+    #[serde(rename = "random")]
+    #[strum(serialize = "random")]
+    Random,
+    /// tgm1, rerolls up to 4 times to avoid the last 4 pieces
+    #[serde(rename = "tgm-4-roll")]
+    #[strum(serialize = "tgm-4-roll")]
+    Tgm4Roll,
+    /// tgm2, rerolls up to 6 times to avoid the last 4 pieces
+    #[serde(rename = "tgm-6-roll")]
+    #[strum(serialize = "tgm-6-roll")]
+    Tgm6Roll,
+    /// nes tetris, rerolls once when it picks the previous piece
+    #[serde(rename = "nes")]
+    #[strum(serialize = "nes")]
+    Nes,
+    /// jstris's 7-bag, which gives the same pieces as jstris for the same seed
+    #[serde(rename = "jstris")]
+    #[strum(serialize = "jstris")]
+    Jstris,
+}
+
+impl RandomizerKind {
+    pub fn build(self) -> Box<dyn Randomizer> {
+        match self {
+            RandomizerKind::Bag7 => Box::new(Bag { copies: 1, extra: 0 }),
+            RandomizerKind::Bag14 => Box::new(Bag { copies: 2, extra: 0 }),
+            RandomizerKind::Bag7Plus1 => Box::new(Bag { copies: 1, extra: 1 }),
+            RandomizerKind::Random => Box::new(Memoryless),
+            RandomizerKind::Tgm4Roll => Box::new(History::new(4, [Z; 4])),
+            RandomizerKind::Tgm6Roll => Box::new(History::new(6, [Z, S, S, Z])),
+            RandomizerKind::Nes => Box::<Nes>::default(),
+            RandomizerKind::Jstris => Box::new(Jstris { rng: Alea::new("") }),
+        }
+    }
+}
+
+/// `copies` of each piece plus `extra` random ones, shuffled together
+#[derive(Clone)]
+pub struct Bag {
+    pub copies: usize,
+    pub extra: usize,
+}
+
+impl Randomizer for Bag {
+    fn next_batch(&mut self, rng: &mut StdRng) -> Vec<Piece> {
+        let mut pieces = PIECES.repeat(self.copies);
+        for _ in 0..self.extra {
+            pieces.push(*PIECES.choose(rng).unwrap());
+        }
+        pieces.shuffle(rng);
+        pieces
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone)]
+pub struct Memoryless;
+
+impl Randomizer for Memoryless {
+    fn next_batch(&mut self, rng: &mut StdRng) -> Vec<Piece> {
+        vec![*PIECES.choose(rng).unwrap()]
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+/// tgm's randomizer, which rolls up to `rolls` times for a piece that isn't in the last 4 and
+/// never starts with an S, Z or O
+#[derive(Clone)]
+pub struct History {
+    pub rolls: u8,
+    history: [Piece; 4],
+    first: bool,
+}
+
+impl History {
+    /// `history` is what the game pretends came before the first piece
+    pub fn new(rolls: u8, history: [Piece; 4]) -> Self {
+        Self { rolls, history, first: true }
+    }
+}
+
+impl Randomizer for History {
+    fn next_batch(&mut self, rng: &mut StdRng) -> Vec<Piece> {
+        let piece = if self.first {
+            self.first = false;
+            *[I, J, L, T].choose(rng).unwrap()
+        } else {
+            let mut piece = *PIECES.choose(rng).unwrap();
+            for _ in 1..self.rolls {
+                if !self.history.contains(&piece) {
+                    break;
+                }
+                piece = *PIECES.choose(rng).unwrap();
+            }
+            piece
+        };
+        self.history.rotate_left(1);
+        self.history[3] = piece;
+        vec![piece]
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+/// nes tetris rolls an 8 sided die, and rolls again with 7 sides on an 8 or the previous piece
+#[derive(Clone, Default)]
+pub struct Nes {
+    previous: Option<Piece>,
+}
+
+impl Randomizer for Nes {
+    fn next_batch(&mut self, rng: &mut StdRng) -> Vec<Piece> {
+        // the order the nes numbers its pieces in
+        const ORDER: [Piece; 7] = [T, J, Z, O, S, L, I];
+        let piece = match ORDER.get(rng.random_range(0..8)) {
+            Some(&piece) if Some(piece) != self.previous => piece,
+            _ => ORDER[rng.random_range(0..7)],
+        };
+        self.previous = Some(piece);
+        vec![piece]
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+/// Seeds are shared as short strings on jstris, this packs up to 8 ascii characters into a seed
+pub fn seed_from_str(s: &str) -> Option<u64> {
+    let valid = s.len() <= 8 && s.bytes().all(|b| b.is_ascii() && b != 0);
+    valid.then(|| s.bytes().fold(0, |seed, b| seed << 8 | b as u64))
+}
+
+/// The string a seed was packed from by [`seed_from_str`]
+pub fn seed_to_string(seed: u64) -> String {
+    seed.to_be_bytes().into_iter().filter(|&b| b != 0).map(char::from).collect()
+}
+
+/// A random seed that can be shared, 6 characters like the ones jstris makes
+pub fn random_seed(rng: &mut impl Rng) -> u64 {
+    const CHARS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    (0..6).fold(0, |seed, _| seed << 8 | *CHARS.choose(rng).unwrap() as u64)
+}
+
+/// jstris takes pieces out of a 7-bag one at a time using [`Alea`] seeded with the seed string
+#[derive(Clone)]
+pub struct Jstris {
+    rng: Alea,
+}
+
+impl Randomizer for Jstris {
+    fn seed(&mut self, seed: u64) {
+        self.rng = Alea::new(&seed_to_string(seed));
+    }
+
+    fn next_batch(&mut self, _rng: &mut StdRng) -> Vec<Piece> {
+        // the order jstris numbers its pieces in
+        let mut bag = vec![I, O, T, L, J, S, Z];
+        let mut pieces = Vec::with_capacity(bag.len());
+        while !bag.is_empty() {
+            let i = (self.rng.next_f64() * bag.len() as f64) as usize;
+            pieces.push(bag.remove(i));
+        }
+        pieces
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+/// Johannes Baagøe's Alea prng, which jstris uses. Everything is kept in f64s the way javascript
+/// does it so the output matches exactly
+#[derive(Clone)]
+pub struct Alea {
+    s0: f64,
+    s1: f64,
+    s2: f64,
+    c: f64,
+}
+
+impl Alea {
+    pub fn new(seed: &str) -> Self {
+        let mut mash = Mash(0xefc8249d_u32 as f64);
+        let mut s = [mash.mash(" "), mash.mash(" "), mash.mash(" ")];
+        for s in &mut s {
+            *s -= mash.mash(seed);
+            if *s < 0.0 {
+                *s += 1.0;
+            }
+        }
+        Self { s0: s[0], s1: s[1], s2: s[2], c: 1.0 }
+    }
+
+    /// A number from 0 up to but not including 1
+    pub fn next_f64(&mut self) -> f64 {
+        let t = 2091639.0 * self.s0 + self.c * 2.3283064365386963e-10;
+        self.s0 = self.s1;
+        self.s1 = self.s2;
+        // `t | 0` in javascript, `t` is always positive and well within i32
+        self.c = t.trunc();
+        self.s2 = t - self.c;
+        self.s2
+    }
+}
+
+/// Alea's hash for turning the seed into its starting state
+struct Mash(f64);
+
+impl Mash {
+    fn mash(&mut self, data: &str) -> f64 {
+        // `x >>> 0` in javascript, which only ever sees positive numbers here
+        let uint32 = |x: f64| x as u64 as u32 as f64;
+        let mut n = self.0;
+        for unit in data.encode_utf16() {
+            n += unit as f64;
+            let mut h = 0.02519603282416938 * n;
+            n = uint32(h);
+            h -= n;
+            h *= n;
+            n = uint32(h);
+            h -= n;
+            n += h * 4294967296.0;
+        }
+        self.0 = n;
+        uint32(n) * 2.3283064365386963e-10
+    }
+}
@@ -1,24 +1,52 @@
-use std::time::Instant;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
+use web_time::Instant;
 
-use crate::{Config, InputEvent};
+use crate::{
+    Config, Event, Game, InputEvent, Mode, PlacementResult,
+    sound::{Sink, SoundPlayer},
+};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ReplayEvent {
-    elapsed: u16, // millis
-    input: InputEvent,
+    pub elapsed: u16, // millis
+    /// what's left of the time over `elapsed`, so playback lands on exactly the same instants.
+    /// Replays from before this was saved only play back to the millisecond
+    #[serde(default)]
+    pub nanos: u32,
+    pub input: InputEvent,
 }
 
-// TODO: write a PRNG compatible with the one jstris uses so that seeds can be
-// shared harddrop.com/forums/index.php%3Fs=&showtopic=7087&view=findpost&
-// p=92057 also rework soft-drop config to match jstris's
+impl ReplayEvent {
+    /// The time since the previous event
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.elapsed as u64) + Duration::from_nanos(self.nanos as u64)
+    }
+}
+
+/// A stretch of time the game spent paused, which the event timings leave out
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PauseSpan {
+    /// millis of unpaused time since the start
+    pub at: u32,
+    /// millis
+    pub length: u32,
+}
+
+// TODO: rework soft-drop config to match jstris's
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Replay {
     pub length: u32, // millis
+    /// packed from a string by [`seed_from_str`](crate::randomizer::seed_from_str) when shared
     pub seed: u64,
     pub config: Config,
+    /// replays from before modes were saved are all 40 line sprints
+    #[serde(default)]
+    pub mode: Mode,
     pub events: Vec<ReplayEvent>,
+    #[serde(default)]
+    pub pauses: Vec<PauseSpan>,
     // TODO: include optional sounds and skin (by link or directly encoded?)
     // for full roundtrippable replays
     #[serde(skip)]
@@ -26,8 +54,16 @@ pub struct Replay {
 }
 
 impl Replay {
-    pub fn new(config: Config, seed: u64) -> Self {
-        Self { seed, config, events: Default::default(), length: 0, last: None }
+    pub fn new(config: Config, mode: Mode, seed: u64) -> Self {
+        Self {
+            seed,
+            config,
+            mode,
+            events: Default::default(),
+            pauses: Default::default(),
+            length: 0,
+            last: None,
+        }
     }
 
     pub fn start(&mut self) {
@@ -35,8 +71,64 @@ impl Replay {
     }
 
     pub fn push(&mut self, input: InputEvent, t: Instant) {
-        let elapsed = (t - self.last.unwrap()).as_millis() as u16;
+        let since = t - self.last.unwrap();
+        let (elapsed, nanos) = (since.as_millis() as u16, since.subsec_nanos() % 1_000_000);
         self.last = Some(t);
-        self.events.push(ReplayEvent { elapsed, input })
+        self.events.push(ReplayEvent { elapsed, nanos, input })
+    }
+
+    /// Catches the game up to `t` then handles an input and records it, the same way [`play`]
+    /// will replay it. Anything the game ignores is left out, and resuming records the pause
+    ///
+    /// [`play`]: Self::play
+    pub fn record(
+        &mut self,
+        game: &mut Game,
+        input: InputEvent,
+        t: Instant,
+        sound: &SoundPlayer<impl Sink>,
+    ) -> Vec<PlacementResult> {
+        let mut results = game.advance(t, sound);
+        if !game.accepts(input) {
+            return results;
+        }
+        let paused = game.paused;
+        results.extend(game.handle(Event::Input(input), t, sound));
+        if let Some(start) = paused
+            && game.paused.is_none()
+        {
+            self.pause(start, t);
+        }
+        if paused.is_none() || input == InputEvent::Pause {
+            self.push(input, t);
+        }
+        results
+    }
+
+    /// Records the game being paused from `start` until `end`
+    pub fn pause(&mut self, start: Instant, end: Instant) {
+        let last = self.last.unwrap();
+        let before: Duration = self.events.iter().map(ReplayEvent::duration).sum();
+        let at = (before + (start - last)).as_millis() as u32;
+        self.pauses.push(PauseSpan { at, length: (end - start).as_millis() as u32 });
+        // so the next event doesn't count the pause
+        self.last = Some(last + (end - start));
+    }
+
+    /// Plays the inputs back as fast as possible, returning the game as it was after the last
+    /// one. The same replay always ends up the same way
+    pub fn play(&self, sound: &SoundPlayer<impl Sink>) -> Game {
+        let mut game = Game::new(self.config);
+        game.mode = self.mode.clone();
+        let mut t = Instant::now();
+        game.start_at(Some(self.seed), t, sound);
+        for event in &self.events {
+            t += event.duration();
+            game.advance(t, sound);
+            if game.accepts(event.input) {
+                game.handle(Event::Input(event.input), t, sound);
+            }
+        }
+        game
     }
 }
@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+use strum::EnumString;
+
+use crate::{Piece, PieceLocation, Rotation, Spin};
+
+type Kicks = &'static [(i8, i8)];
+
+/// How pieces rotate and where they end up when a rotation is blocked
+///
+/// Pieces always rotate around their center block (the second block for I and the bottom left
+/// for O), then get moved by the system's [`offset`](RotationSystem::offset) difference between the
+/// two orientations, and finally try each [`kick`](RotationSystem::kicks) in order
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum RotationSystem {
+    /// the guideline's super rotation system
+    Srs,
+    /// tetr.io's SRS+, with symmetric I kicks
+    #[default]
+    SrsPlus,
+    /// arika's rotation system from tgm, bottom aligned with only left and right kicks
+    Ars,
+    /// the nes's rotation system, which never kicks
+    Nrs,
+    /// SRS without any kicks
+    NoKicks,
+}
+
+/// Which kicks 180 rotations use, on top of the rotation system's offsets
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum FlipKicks {
+    #[default]
+    Tetrio,
+    /// SRS-X's big table, which tries moving sideways before up
+    Jstris,
+    /// 180 rotation is turned off
+    None,
+}
+
+// SRS kicks from: https://harddrop.com/wiki/SRS#How_guideline_SRS_actually_works
+// I spins are slightly asymetrical, see https://harddrop.com/wiki/I-spins_in_SRS
+const SRS_N_E: Kicks = &[(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)];
+const SRS_E_N: Kicks = &[(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)];
+const SRS_S_W: Kicks = &[(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)];
+const SRS_W_S: Kicks = &[(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)];
+
+const SRS_I_N_E: Kicks = &[(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)];
+const SRS_I_E_N: Kicks = &[(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)];
+const SRS_I_E_S: Kicks = &[(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)];
+const SRS_I_S_E: Kicks = &[(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)];
+
+// tetr.io's SRS+ mirrors the I kicks so both directions behave the same
+const SRS_PLUS_I: [Kicks; 8] = [
+    &[(0, 0), (1, 0), (-2, 0), (-2, -1), (1, 2)], // north to east
+    &[(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)], // east to north
+    &[(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)], // east to south
+    &[(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)], // south to east
+    &[(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)], // south to west
+    &[(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)], // west to south
+    &[(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)], // west to north
+    &[(0, 0), (-1, 0), (2, 0), (2, -1), (-1, 2)], // north to west
+];
+
+// 180 kicks from: https://tetrio.wiki.gg/images/5/52/TETR.IO_180kicks.png?6d5d9d
+const FLIP_N_S: Kicks = &[(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)];
+const FLIP_S_N: Kicks = &[(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)];
+const FLIP_E_W: Kicks = &[(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)];
+const FLIP_W_E: Kicks = &[(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)];
+const FLIP_I_N_S: Kicks = &[(0, 0), (0, 1)];
+const FLIP_I_S_N: Kicks = &[(0, 0), (0, -1)];
+const FLIP_I_E_W: Kicks = &[(0, 0), (1, 0)];
+const FLIP_I_W_E: Kicks = &[(0, 0), (-1, 0)];
+
+// jstris uses the same 180 kicks as SRS-X from nullpomino
+const JSTRIS_N_S: Kicks = &[
+    (0, 0),
+    (1, 0),
+    (2, 0),
+    (1, 1),
+    (2, 1),
+    (-1, 0),
+    (-2, 0),
+    (-1, 1),
+    (-2, 1),
+    (0, -1),
+    (3, 0),
+    (-3, 0),
+];
+const JSTRIS_S_N: Kicks = &[
+    (0, 0),
+    (-1, 0),
+    (-2, 0),
+    (-1, -1),
+    (-2, -1),
+    (1, 0),
+    (2, 0),
+    (1, -1),
+    (2, -1),
+    (0, 1),
+    (-3, 0),
+    (3, 0),
+];
+const JSTRIS_E_W: Kicks = &[
+    (0, 0),
+    (0, 1),
+    (0, 2),
+    (-1, 1),
+    (-1, 2),
+    (0, -1),
+    (0, -2),
+    (-1, -1),
+    (-1, -2),
+    (1, 0),
+    (0, 3),
+    (0, -3),
+];
+const JSTRIS_W_E: Kicks = &[
+    (0, 0),
+    (0, 1),
+    (0, 2),
+    (1, 1),
+    (1, 2),
+    (0, -1),
+    (0, -2),
+    (1, -1),
+    (1, -2),
+    (-1, 0),
+    (0, 3),
+    (0, -3),
+];
+const JSTRIS_I_N_S: Kicks = &[(0, 0), (-1, 0), (-2, 0), (1, 0), (2, 0), (0, 1)];
+const JSTRIS_I_S_N: Kicks = &[(0, 0), (1, 0), (2, 0), (-1, 0), (-2, 0), (0, -1)];
+const JSTRIS_I_E_W: Kicks = &[(0, 0), (0, 1), (0, 2), (0, -1), (0, -2), (-1, 0)];
+const JSTRIS_I_W_E: Kicks = &[(0, 0), (0, 1), (0, 2), (0, -1), (0, -2), (1, 0)];
+
+/// tgm tries one to the right before one to the left
+const ARS: Kicks = &[(0, 0), (1, 0), (-1, 0)];
+const NONE: Kicks = &[(0, 0)];
+
+impl RotationSystem {
+    /// The orientation pieces spawn in, ars and nrs have T, J and L pointing down
+    pub fn spawn_rotation(self, piece: Piece) -> Rotation {
+        match (self, piece) {
+            (RotationSystem::Ars | RotationSystem::Nrs, Piece::T | Piece::J | Piece::L) => {
+                Rotation::South
+            }
+            _ => Rotation::North,
+        }
+    }
+
+    /// Where a piece in this orientation sits relative to rotating it around its center block.
+    /// Rotating moves the piece by the difference between the two orientations' offsets
+    pub fn offset(self, piece: Piece, rot: Rotation) -> (i8, i8) {
+        use {Piece::*, Rotation::*};
+        match (self, piece, rot) {
+            // the O never actually moves
+            (_, O, North) => (0, 0),
+            (_, O, East) => (0, 1),
+            (_, O, South) => (1, 1),
+            (_, O, West) => (1, 0),
+            // SRS turns the I around the middle of its 4x4 box
+            (RotationSystem::Srs | RotationSystem::SrsPlus | RotationSystem::NoKicks, I, rot) => {
+                [(0, 0), (1, 0), (1, -1), (0, -1)][rot as usize]
+            }
+            (RotationSystem::Srs | RotationSystem::SrsPlus | RotationSystem::NoKicks, ..) => (0, 0),
+            // ars and nrs only have 2 orientations for I, S, and Z
+            (RotationSystem::Ars, I, rot) => [(0, 0), (1, 0), (1, 0), (1, -1)][rot as usize],
+            (RotationSystem::Nrs, I, rot) => [(0, 0), (1, 1), (1, 0), (1, 0)][rot as usize],
+            // ars stands S up on the left of its box and nrs on the right, both use the right for Z
+            (RotationSystem::Ars, S, rot) => [(0, -1), (-1, 0), (0, 0), (0, 0)][rot as usize],
+            (_, S | Z, rot) => [(0, -1), (0, 0), (0, 0), (1, 0)][rot as usize],
+            // ars keeps pieces bottom aligned, so pointing up sits a row lower
+            (RotationSystem::Ars, _, North) => (0, -1),
+            _ => (0, 0),
+        }
+    }
+
+    /// Whether 180s use [`FlipKicks`], the other systems only ever flip in place
+    pub fn has_flip_kicks(self) -> bool {
+        matches!(self, RotationSystem::Srs | RotationSystem::SrsPlus)
+    }
+
+    /// Positions to try in order after rotating, relative to where the offset puts the piece. 180s
+    /// don't kick here, SRS and SRS+ use [`FlipKicks`] for them instead
+    pub fn kicks(self, piece: Piece, rot: Rotation, dir: Spin) -> Kicks {
+        use {Piece::*, Rotation::*};
+        let next_rot = rot.rotate(dir);
+        let srs = match (piece, rot, next_rot) {
+            (O, ..) => NONE,
+            // see `FlipKicks`
+            (_, North, South) | (_, South, North) | (_, East, West) | (_, West, East) => NONE,
+            (I, ..) if self == RotationSystem::SrsPlus => {
+                let i = match (rot, next_rot) {
+                    (North, East) => 0,
+                    (East, North) => 1,
+                    (East, South) => 2,
+                    (South, East) => 3,
+                    (South, West) => 4,
+                    (West, South) => 5,
+                    (West, North) => 6,
+                    _ => 7,
+                };
+                SRS_PLUS_I[i]
+            }
+            (I, North, East) | (I, West, South) => SRS_I_N_E,
+            (I, East, North) | (I, South, West) => SRS_I_E_N,
+            (I, East, South) | (I, North, West) => SRS_I_E_S,
+            (I, South, East) | (I, West, North) => SRS_I_S_E,
+            (_, North, East) | (_, South, East) => SRS_N_E,
+            (_, East, North) | (_, East, South) => SRS_E_N,
+            (_, South, West) | (_, North, West) => SRS_S_W,
+            (_, West, South) | (_, West, North) => SRS_W_S,
+            _ => unreachable!(),
+        };
+        match self {
+            RotationSystem::Srs | RotationSystem::SrsPlus => srs,
+            RotationSystem::Ars if piece != I && dir != Spin::Flip => ARS,
+            _ => NONE,
+        }
+    }
+
+    /// ars's center column rule: a J, L, or T whose rotation is blocked can only kick if the first
+    /// blocked cell of its 3x3 box, reading from the top left, isn't in the middle column
+    pub fn can_kick(self, rotated: PieceLocation, is_filled: impl Fn((i8, i8)) -> bool) -> bool {
+        if self != RotationSystem::Ars || !matches!(rotated.piece, Piece::J | Piece::L | Piece::T) {
+            return true;
+        }
+        let (ox, oy) = self.offset(rotated.piece, rotated.rot);
+        let (cx, cy) = (rotated.pos.0 - ox, rotated.pos.1 - oy);
+        let blocks = rotated.blocks();
+        let blocked = (-1..=1)
+            .rev()
+            .flat_map(|dy| (-1..=1).map(move |dx| (cx + dx, cy + dy)))
+            .find(|&pos| blocks.contains(&pos) && is_filled(pos));
+        blocked.is_none_or(|(x, _)| x != cx)
+    }
+}
+
+impl FlipKicks {
+    /// Positions to try in order after flipping a piece that was in `rot`, or `None` when 180s
+    /// are turned off
+    pub fn kicks(self, piece: Piece, rot: Rotation) -> Option<Kicks> {
+        use {Piece::*, Rotation::*};
+        Some(match (self, piece, rot) {
+            (FlipKicks::None, ..) => return None,
+            (_, O, _) => NONE,
+            (FlipKicks::Tetrio, I, North) => FLIP_I_N_S,
+            (FlipKicks::Tetrio, I, South) => FLIP_I_S_N,
+            (FlipKicks::Tetrio, I, East) => FLIP_I_E_W,
+            (FlipKicks::Tetrio, I, West) => FLIP_I_W_E,
+            (FlipKicks::Tetrio, _, North) => FLIP_N_S,
+            (FlipKicks::Tetrio, _, South) => FLIP_S_N,
+            (FlipKicks::Tetrio, _, East) => FLIP_E_W,
+            (FlipKicks::Tetrio, _, West) => FLIP_W_E,
+            (FlipKicks::Jstris, I, North) => JSTRIS_I_N_S,
+            (FlipKicks::Jstris, I, South) => JSTRIS_I_S_N,
+            (FlipKicks::Jstris, I, East) => JSTRIS_I_E_W,
+            (FlipKicks::Jstris, I, West) => JSTRIS_I_W_E,
+            (FlipKicks::Jstris, _, North) => JSTRIS_N_S,
+            (FlipKicks::Jstris, _, South) => JSTRIS_S_N,
+            (FlipKicks::Jstris, _, East) => JSTRIS_E_W,
+            (FlipKicks::Jstris, _, West) => JSTRIS_W_E,
+        })
+    }
+}
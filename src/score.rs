@@ -0,0 +1,30 @@
+use crate::{PlacementResult, SpinKind};
+
+/// points per row the piece moves while soft dropping
+pub const SOFT_DROP: u32 = 1;
+/// points per row the piece falls when hard dropped
+pub const HARD_DROP: u32 = 2;
+const COMBO: u32 = 50;
+
+/// Guideline points for a placement, not counting drop points
+pub fn score(placement: &PlacementResult) -> u32 {
+    let lines = placement.rows.len();
+    let base = match placement.spin {
+        SpinKind::None => [0, 100, 300, 500, 800][lines.min(4)],
+        SpinKind::Mini => [100, 200, 400][lines.min(2)],
+        SpinKind::Full => [400, 800, 1200, 1600][lines.min(3)],
+    };
+    // an easy clear would've reset the chain, so any clear with a chain going is difficult
+    let b2b = lines > 0 && placement.b2b > 1;
+    let base = if b2b { base * 3 / 2 } else { base };
+    let combo = COMBO * placement.combo.saturating_sub(1) as u32;
+    let pc = match (placement.perfect_clear, lines) {
+        (false, _) => 0,
+        (true, 1) => 800,
+        (true, 2) => 1200,
+        (true, 3) => 1800,
+        (true, _) if b2b => 3200,
+        (true, _) => 2000,
+    };
+    base + combo + pc
+}
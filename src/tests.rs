@@ -1,4 +1,4 @@
-use std::time::Instant;
+use std::{collections::BTreeSet, time::Instant};
 
 use fumen::{CellColor, Fumen, PieceType, RotationState};
 use ringbuffer::RingBuffer;
@@ -54,10 +54,12 @@ impl From<CellColor> for Cell {
     }
 }
 
-fn get_board(page: &fumen::Page) -> game::Board {
-    let mut board: game::Board = [[Cell::Empty; 10]; 50];
-    for (row, from) in board.iter_mut().zip(page.field) {
-        *row = from.map(Into::into);
+fn get_board(page: &fumen::Page) -> Board {
+    let mut board = Board::default();
+    for (y, row) in page.field.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            board.set(x, y, cell.into());
+        }
     }
     board
 }
@@ -85,7 +87,7 @@ impl std::fmt::Debug for BoardString {
     }
 }
 
-fn render(board: game::Board, loc: PieceLocation) -> BoardString {
+fn render(board: Board, loc: PieceLocation) -> BoardString {
     let mut s = BoardString("\n----------------------\n".to_owned());
     for y in (0..20).rev() {
         s.0.push('|');
@@ -109,15 +111,23 @@ fn render(board: game::Board, loc: PieceLocation) -> BoardString {
 
 // TODO: show comments in assertion failures?
 // TODO: render both boards side by side in tui? with diff? detect tty to change from color to letter based skin
-fn run_fumen<T, U, V>(data: &str, events: T)
+fn run_fumen<T, U, V>(data: &str, events: T) -> Game
+where
+    T: Iterator<Item = U> + ExactSizeIterator,
+    U: IntoIterator<Item = V>,
+    V: Into<Event>,
+{
+    run_fumen_with(Config::default(), data, events)
+}
+
+fn run_fumen_with<T, U, V>(config: Config, data: &str, events: T) -> Game
 where
     T: Iterator<Item = U> + ExactSizeIterator,
     U: IntoIterator<Item = V>,
     V: Into<Event>,
 {
     let f = Fumen::decode(data).unwrap();
-    let c = Config::default();
-    let mut g = Game::new(c);
+    let mut g = Game::new(config);
     let t = Instant::now();
     let first = f.pages.first().unwrap();
     g.board = get_board(first);
@@ -137,6 +147,7 @@ where
         );
         println!("{}", render(g.board, g.current));
     }
+    g
 }
 
 // use https://fumen.zui.jp/ to open any of the following data strings
@@ -171,3 +182,1052 @@ fn test_basic() {
 fn test_right_side_i_kick() {
     run_fumen("v115@WhR4GeR4Ne5InvhBhxBAAA", [[Ccw], [Hard]].into_iter());
 }
+
+#[test]
+fn test_rotation_systems() {
+    use RotationSystem::*;
+    let run = |rotation_system, data, events: &[&[InputEvent]]| {
+        let config = Config { rotation_system, ..Default::default() };
+        run_fumen_with(config, data, events.iter().map(|e| e.iter().copied()));
+    };
+    // an I rotating into a block: SRS kicks it left, SRS+ right, and the others don't kick it
+    run(Srs, "v115@4gA81eR3mvhApWf", &[&[Cw]]);
+    run(SrsPlus, "v115@4gA81eR3mvhAJYf", &[&[Cw]]);
+    for system in [Ars, Nrs, NoKicks] {
+        run(system, "v115@4gA81eR3mvhARXf", &[&[Cw]]);
+    }
+
+    // a J pointing down, the way ars and nrs spawn it, rotating into a block on its left kicks
+    // right in both ars and SRS
+    run(Ars, "v115@2gA83eG3mvhA+Xf", &[&[Cw]]);
+    run(Srs, "v115@2gA83eG3mvhA+Xf", &[&[Cw]]);
+    run(Nrs, "v115@2gA83eG3mvhAGXf", &[&[Cw]]);
+    // but ars's center column rule stops it when the block is above the middle
+    run(Ars, "v115@jgA8KfG3mvhAGXf", &[&[Cw]]);
+    run(Srs, "v115@jgA8KfG3mvhA+Xf", &[&[Cw]]);
+
+    // nrs's I goes back to the same cells after 2 rotations, SRS's moves down a row
+    run(Nrs, "v115@vhCR3mpSfRXf", &[&[Cw], &[Cw]]);
+    run(Srs, "v115@vhCR3mpXfBcf", &[&[Cw], &[Cw]]);
+    // ars keeps pieces bottom aligned, so a T flipping from pointing down to up moves down a row
+    run(Ars, "v115@vhBF3mVcf", &[&[Cw, Cw]]);
+    run(Srs, "v115@vhBF3mVXf", &[&[Cw, Cw]]);
+    // the O doesn't move in any of them
+    for system in [Srs, SrsPlus, Ars, Nrs, NoKicks] {
+        run(system, "v115@vhBTymTSf", &[&[Cw, Cw, Ccw]]);
+    }
+    // only SRS and SRS+ kick 180s, so a T flipping into a block below it stays put
+    for system in [Nrs, NoKicks] {
+        run(system, "v115@3gA82eV3mvhAVXf", &[&[Flip]]);
+    }
+
+    let sound = SoundPlayer::<NullSink>::default();
+    let mut g = Game::new(Config { rotation_system: Ars, ..Default::default() });
+    g.start(Some(0), &sound);
+    let (time, start) = g.timers.pop_front().unwrap();
+    g.handle(Event::Timer(start), time, &sound);
+    let expected = if matches!(g.current.piece, Piece::T | Piece::J | Piece::L) {
+        Rotation::South
+    } else {
+        Rotation::North
+    };
+    assert_eq!(g.current.rot, expected);
+}
+
+#[test]
+fn test_flip_kicks() {
+    // a T flipping into a block below it: tetr.io kicks it up, jstris to the right
+    for (flip_kicks, data) in [
+        (FlipKicks::Tetrio, "v115@3gA82eV3mvhAFSf"),
+        (FlipKicks::Jstris, "v115@3gA82eV3mvhAlXf"),
+        (FlipKicks::None, "v115@3gA82eV3mvhAVXf"),
+    ] {
+        run_fumen_with(Config { flip_kicks, ..Default::default() }, data, [[Flip]].into_iter());
+    }
+    // turning 180s off stops them even when there's room
+    let config = Config { flip_kicks: FlipKicks::None, ..Default::default() };
+    let g = run_fumen_with(config, "v115@vhBF3mFXf", [[Flip]].into_iter());
+    assert_eq!(g.current.rot, Rotation::South, "the T shouldn't flip");
+}
+
+#[test]
+fn test_tspin_double() {
+    let g = run_fumen("v115@HhjlFeilCenlAeklJeNLnvhAFrBvhAAAA", [[Cw], [Hard]].into_iter());
+    let result = g.last_placement.unwrap();
+    assert_eq!(result.spin, SpinKind::Full);
+    assert_eq!(result.rows, [0, 1]);
+    assert_eq!(result.to_string(), "T-Spin Double");
+}
+
+#[test]
+fn test_tspin_mini() {
+    let g = run_fumen("v115@HhglAeglJenlBemlJetJnvhAlpBvhAAAA", [[Cw], [Hard]].into_iter());
+    let result = g.last_placement.unwrap();
+    assert_eq!(result.spin, SpinKind::Mini);
+    assert_eq!(result.rows, [1]);
+    assert_eq!(result.to_string(), "Mini T-Spin Single");
+}
+
+#[test]
+fn test_spin_rules() {
+    for (spin_rule, expected) in [
+        (SpinRule::None, SpinKind::None),
+        (SpinRule::TSpinOnly, SpinKind::None),
+        (SpinRule::AllSpin, SpinKind::Full),
+        (SpinRule::AllMini, SpinKind::Mini),
+    ] {
+        let (mut g, t) = start_floating(Config { spin_rule, ..Default::default() }, 4);
+        for (x, y) in [(2, 0), (0, 1), (2, 3)] {
+            g.board.set(x, y, Cell::Garbage);
+        }
+        g.current = PieceLocation::new(Piece::Z, (1, 1), Rotation::North);
+        press(&mut g, Cw, t);
+        assert!(g.current.pos == (1, 1), "should rotate in place");
+        let result = press(&mut g, Hard, t).unwrap();
+        assert_eq!(result.spin, expected, "{spin_rule:?} should credit {expected:?}");
+    }
+}
+
+#[test]
+fn test_no_spin_without_rotation() {
+    let f = Fumen::decode("v115@HhglAeglJenlBemlJetJnvhAlpBvhAAAA").unwrap();
+    let (mut g, t) = start_floating(Config::default(), 4);
+    g.board = get_board(&f.pages[0]);
+    g.current = PieceLocation::new(Piece::T, (1, 1), Rotation::South);
+    let result = press(&mut g, Hard, t).unwrap();
+    assert_eq!(result.spin, SpinKind::None);
+    assert_eq!(result.to_string(), "Single");
+}
+
+/// Fires every timer that's due by `until`, in order, at the time it was scheduled for
+fn advance(g: &mut Game, until: Instant) {
+    g.advance(until, &SoundPlayer::<NullSink>::default());
+}
+
+/// Starts a game with no gravity and a T piece floating in the middle of an empty board
+fn start_floating(config: Config, x: i8) -> (Game, Instant) {
+    let mut g = Game::new(Config { gravity: None, ..config });
+    g.start(Some(0), &SoundPlayer::<NullSink>::default());
+    let t = g.time + FRAME * 60;
+    advance(&mut g, t);
+    assert!(g.state == GameState::Running);
+    g.current = PieceLocation::new(Piece::T, (x, 10), Rotation::North);
+    (g, t)
+}
+
+fn press(g: &mut Game, event: InputEvent, t: Instant) -> Option<PlacementResult> {
+    advance(g, t);
+    g.handle(event.into(), t, &SoundPlayer::<NullSink>::default())
+}
+
+#[test]
+fn test_das_arr() {
+    let (mut g, t) = start_floating(Config { das: 10, arr: 2, ..Default::default() }, 6);
+    press(&mut g, PressLeft, t);
+    assert_eq!(g.current.pos.0, 5);
+    advance(&mut g, t + FRAME * 9);
+    assert_eq!(g.current.pos.0, 5, "shouldn't move before das charges");
+    advance(&mut g, t + FRAME * 10);
+    assert_eq!(g.current.pos.0, 4, "should shift as soon as das charges");
+    advance(&mut g, t + FRAME * 11);
+    assert_eq!(g.current.pos.0, 4);
+    advance(&mut g, t + FRAME * 12);
+    assert_eq!(g.current.pos.0, 3);
+    advance(&mut g, t + FRAME * 20);
+    assert_eq!(g.current.pos.0, 1, "should stop at the wall");
+    press(&mut g, ReleaseLeft, t + FRAME * 21);
+    assert!(g.timers.iter().all(|&(_, ev)| ev != TimerEvent::Arr));
+}
+
+#[test]
+fn test_instant_arr() {
+    let (mut g, t) = start_floating(Config { das: 10, arr: 0, ..Default::default() }, 6);
+    press(&mut g, PressRight, t);
+    assert_eq!(g.current.pos.0, 7);
+    advance(&mut g, t + FRAME * 10);
+    assert_eq!(g.current.pos.0, 8);
+}
+
+#[test]
+fn test_das_both_directions() {
+    let (mut g, t) = start_floating(Config { das: 10, arr: 2, ..Default::default() }, 6);
+    press(&mut g, PressLeft, t);
+    advance(&mut g, t + FRAME * 12);
+    assert_eq!(g.current.pos.0, 3);
+
+    // the newest direction takes priority and has to charge its own das
+    press(&mut g, PressRight, t + FRAME * 13);
+    assert_eq!(g.current.pos.0, 4);
+    advance(&mut g, t + FRAME * 20);
+    assert_eq!(g.current.pos.0, 4);
+
+    // releasing it hands control back to the (still charged) left key
+    press(&mut g, ReleaseRight, t + FRAME * 20);
+    assert_eq!(g.current.pos.0, 3);
+    advance(&mut g, t + FRAME * 22);
+    assert_eq!(g.current.pos.0, 2);
+}
+
+#[test]
+fn test_das_resumes_after_override() {
+    let (mut g, t) = start_floating(Config { das: 10, arr: 2, ..Default::default() }, 4);
+    press(&mut g, PressRight, t);
+    press(&mut g, PressLeft, t + FRAME * 5);
+    press(&mut g, ReleaseLeft, t + FRAME * 8);
+    assert_eq!(g.current.pos.0, 4);
+    advance(&mut g, t + FRAME * 9);
+    assert_eq!(g.current.pos.0, 4);
+    advance(&mut g, t + FRAME * 10);
+    assert_eq!(g.current.pos.0, 5, "right should still charge on its original schedule");
+    advance(&mut g, t + FRAME * 12);
+    assert_eq!(g.current.pos.0, 6);
+}
+
+#[test]
+fn test_arr_resets_lock_delay() {
+    let (mut g, t) = start_floating(Config { das: 10, arr: 2, ..Default::default() }, 6);
+    g.current.pos.1 = 0;
+    press(&mut g, PressLeft, t);
+    advance(&mut g, t + FRAME * 12);
+    let lock = |g: &Game| g.timers.iter().find(|&&(_, ev)| ev == TimerEvent::Lock).unwrap().0;
+    assert_eq!(lock(&g), t + FRAME * (12 + g.config.lock_delay.0 as u32));
+    let extended = g.timers.iter().filter(|&&(_, ev)| ev == TimerEvent::Extended).count();
+    assert_eq!(extended, 1);
+}
+
+#[test]
+fn test_placement_result() {
+    let (mut g, t) = start_floating(Config::default(), 4);
+    for x in (0..10).filter(|x| !(3..=6).contains(x)) {
+        g.board.set(x, 0, Cell::Piece(Piece::L));
+    }
+    g.current = PieceLocation::new(Piece::I, (4, 10), Rotation::North);
+    let result = press(&mut g, Hard, t).unwrap();
+    assert_eq!(result.location, PieceLocation::new(Piece::I, (4, 0), Rotation::North));
+    assert!(result.perfect_clear);
+    assert_eq!(result.top_out, None);
+    assert_eq!(result.to_string(), "Single Perfect Clear");
+    assert_eq!(press(&mut g, Cw, t), None);
+
+    for y in 0..23 {
+        g.board.set(4, y, Cell::Garbage);
+    }
+    g.current = PieceLocation::new(Piece::O, (0, 10), Rotation::North);
+    let result = press(&mut g, Hard, t).unwrap();
+    assert!(!result.perfect_clear);
+    assert_eq!(result.top_out, Some(TopOut::BlockOut));
+    assert_eq!(g.state.outcome(), Some(Outcome::ToppedOut(TopOut::BlockOut)));
+}
+
+#[test]
+fn test_top_out() {
+    let fill = |g: &mut Game, xs: std::ops::RangeInclusive<usize>, rows| {
+        for x in xs {
+            for y in 0..rows {
+                g.board.set(x, y, Cell::Garbage);
+            }
+        }
+    };
+    let (mut g, t) = start_floating(Config::default(), 4);
+    fill(&mut g, 3..=6, 21);
+    g.current = PieceLocation::new(Piece::O, (4, 25), Rotation::North);
+    assert_eq!(press(&mut g, Hard, t).unwrap().top_out, Some(TopOut::LockOut));
+    assert_eq!(g.state.outcome(), Some(Outcome::ToppedOut(TopOut::LockOut)));
+
+    // a vertical I in the corner, half in and half out of the visible board
+    let i = PieceLocation::new(Piece::I, (0, 25), Rotation::East);
+    let i = PieceLocation { pos: (i.pos.0 - i.blocks()[0].0, 25), ..i };
+    for (lock_out, top_out) in
+        [(LockOut::Full, None), (LockOut::Partial, Some(TopOut::PartialLockOut))]
+    {
+        let (mut g, t) = start_floating(Config { lock_out, ..Default::default() }, 4);
+        fill(&mut g, 0..=0, 18);
+        g.current = i;
+        assert_eq!(press(&mut g, Hard, t).unwrap().top_out, top_out);
+    }
+
+    let (mut g, t) = start_floating(Config::default(), 4);
+    fill(&mut g, 3..=6, 23);
+    press(&mut g, Hold, t);
+    assert_eq!(g.state.outcome(), Some(Outcome::ToppedOut(TopOut::BlockOut)));
+
+    let (mut g, t) = start_floating(Config::default(), 4);
+    fill(&mut g, 0..=0, 28);
+    press(&mut g, InputEvent::Garbage(3), t);
+    advance(&mut g, t + FRAME * 20);
+    let outcome = Some(Outcome::ToppedOut(TopOut::GarbageOut));
+    assert_eq!(g.state.outcome(), outcome, "the stack got pushed past the buffer");
+
+    let (mut g, t) = start_floating(Config::default(), 4);
+    press(&mut g, Pause, t);
+    g.abort(t + Duration::from_secs(5));
+    let GameState::Done(result) = g.state else { panic!("the game should be over") };
+    assert_eq!(result.outcome, Outcome::Aborted);
+    assert_eq!(result.stats.time, Duration::ZERO, "time spent paused doesn't count");
+}
+
+#[test]
+fn test_hold_rules() {
+    let (mut g, t) = start_floating(Config { hold: HoldRule::None, ..Default::default() }, 4);
+    press(&mut g, Hold, t);
+    assert!(g.hold.is_empty() && g.current.piece == Piece::T, "hold is turned off");
+
+    let (mut g, t) = start_floating(Config { hold: HoldRule::Unlimited, ..Default::default() }, 4);
+    let next = g.upcomming[0];
+    press(&mut g, Hold, t);
+    press(&mut g, Hold, t);
+    assert_eq!(g.hold, [next]);
+    assert_eq!(g.current.piece, Piece::T, "held twice without placing anything");
+
+    let config = Config { hold: HoldRule::Unlimited, hold_slots: 2, ..Default::default() };
+    let (mut g, t) = start_floating(config, 4);
+    let next = [g.upcomming[0], g.upcomming[1]];
+    press(&mut g, Hold, t);
+    press(&mut g, Hold, t);
+    assert_eq!(g.hold, [Piece::T, next[0]]);
+    assert_eq!(g.current.piece, next[1], "both slots are filled from the queue");
+    press(&mut g, Hold, t);
+    assert_eq!(g.hold, [next[0], next[1]]);
+    assert_eq!(g.current.piece, Piece::T, "the oldest held piece comes back out");
+
+    let (mut g, t) = start_floating(Config { hold_slots: 2, ..Default::default() }, 4);
+    press(&mut g, Hold, t);
+    press(&mut g, Hold, t);
+    assert_eq!(g.hold, [Piece::T], "one hold per piece");
+}
+
+#[test]
+fn test_search_hold() {
+    use tetrizz::data::Piece as P;
+    let lab = Mode::TrainingLab { lookahead: None, search: true, mino_mode: false };
+    let (mut g, _) = start_floating(Config { hold: HoldRule::None, ..Default::default() }, 4);
+    g.mode = lab.clone();
+    assert!(g.search_enabled());
+    let (tetrizz_game, queue) = g.as_tetrizz_game_and_queue();
+    assert_eq!(tetrizz_game.hold, P::T, "the current piece stands in for the missing hold");
+    assert_eq!(queue.len(), 6);
+    let next = g.upcomming[0];
+    assert_ne!(next, Piece::T);
+    assert!(g.can_follow([P::T, next.into()]));
+    assert!(!g.can_follow([next.into()]), "playing the next piece first needs a hold");
+    assert!(!g.can_follow([P::T, P::T]));
+
+    let (mut g, _) = start_floating(Config::default(), 4);
+    g.mode = lab.clone();
+    assert!(g.can_follow([g.upcomming[0].into()]));
+    g.config.hold_slots = 2;
+    assert!(!g.search_enabled(), "the search only knows about one hold slot");
+}
+
+#[test]
+fn test_preview() {
+    for randomizer in [RandomizerKind::Bag7, RandomizerKind::Random, RandomizerKind::Nes] {
+        let (mut g, t) = start_floating(Config { randomizer, ..Default::default() }, 4);
+        for _ in 0..20 {
+            assert!(g.upcomming.len() >= game::MAX_PREVIEW as usize, "{randomizer:?}");
+            press(&mut g, Hard, t);
+        }
+    }
+
+    use tetrizz::data::Piece as P;
+    let (g, _) = start_floating(Config { preview: 2, ..Default::default() }, 4);
+    let (tetrizz_game, queue) = g.as_tetrizz_game_and_queue();
+    assert!(matches!(tetrizz_game.hold, P::T));
+    assert_eq!(queue.len(), 2, "the search only sees the preview");
+
+    let (g, _) = start_floating(Config { preview: 0, ..Default::default() }, 4);
+    let (tetrizz_game, queue) = g.as_tetrizz_game_and_queue();
+    assert!(
+        matches!((tetrizz_game.hold, queue.as_slice()), (P::T, [P::T])),
+        "nothing to hold into"
+    );
+}
+
+#[test]
+fn test_combo_and_b2b() {
+    let (mut g, t) = start_floating(Config::default(), 4);
+    g.mode = Mode::TrainingLab { lookahead: None, search: false, mino_mode: false };
+    let fill = |g: &mut Game, rows: std::ops::Range<usize>| {
+        for y in rows {
+            for x in 1..10 {
+                g.board.set(x, y, Cell::Piece(Piece::L));
+            }
+        }
+    };
+    let drop = |g: &mut Game, piece, x, rot| {
+        g.current = PieceLocation::new(piece, (x, 10), rot);
+        press(g, Hard, t).unwrap()
+    };
+
+    fill(&mut g, 0..4);
+    let quad = drop(&mut g, Piece::I, 0, Rotation::East);
+    assert_eq!((quad.combo, quad.b2b), (1, 1));
+    let miss = drop(&mut g, Piece::O, 4, Rotation::North);
+    assert_eq!((miss.combo, miss.b2b), (0, 1), "b2b shouldn't break without a clear");
+    fill(&mut g, 0..1);
+    let single = drop(&mut g, Piece::I, 0, Rotation::East);
+    assert_eq!((single.combo, single.b2b), (1, 0));
+    assert_eq!((g.combo, g.b2b, g.lines), (1, 0, 5));
+
+    assert_eq!(g.attack, 4 + 10, "the quad was also a perfect clear");
+
+    press(&mut g, Undo, t);
+    assert_eq!((g.combo, g.b2b, g.lines, g.attack), (0, 1, 4, 14));
+}
+
+#[test]
+fn test_undo_redo() {
+    let (mut g, t) = start_floating(Config::default(), 4);
+    let lookahead = game::Lookahead::new(2, 30);
+    g.mode = Mode::TrainingLab { lookahead: Some(lookahead), search: false, mino_mode: false };
+    let drop = |g: &mut Game, x| {
+        g.current = PieceLocation::new(Piece::O, (x, 10), Rotation::North);
+        press(g, Hard, t).unwrap()
+    };
+
+    drop(&mut g, 0);
+    drop(&mut g, 2);
+    assert!(!g.should_draw_board());
+    let after = g.board;
+    press(&mut g, Undo, t);
+    press(&mut g, Undo, t);
+    assert!(g.should_draw_board());
+    assert_eq!((g.pieces, g.redo.len()), (0, 2));
+    press(&mut g, Redo, t);
+    press(&mut g, Redo, t);
+    assert!(g.board == after);
+    assert!(g.should_draw_board(), "redo shouldn't hide the board");
+    assert_eq!((g.pieces, g.history.len(), g.redo.len()), (2, 2, 0));
+    assert_eq!(press(&mut g, Redo, t), None, "nothing left to redo");
+
+    press(&mut g, Undo, t);
+    drop(&mut g, 6);
+    assert!(g.redo.is_empty(), "a new placement replaces what was undone");
+    press(&mut g, Redo, t);
+    assert_eq!(g.pieces, 2);
+    assert!(g.board[0][6] != Cell::Empty && g.board[0][2] == Cell::Empty);
+
+    press(&mut g, Undo, t);
+    g.start(Some(0), &SoundPlayer::<NullSink>::default());
+    let t = g.time + FRAME * 60;
+    assert_eq!(press(&mut g, Redo, t), None, "the old game can't be redone");
+    assert!(g.board == Board::default());
+
+    let moment = g.history[0].clone();
+    g.history = std::iter::repeat_n(moment.clone(), 200).collect();
+    g.redo.push(moment);
+    press(&mut g, Redo, t);
+    assert_eq!(g.history.len(), 200, "redo keeps to the same limit as placing");
+}
+
+#[test]
+fn test_undo_pending_garbage() {
+    let (mut g, t) = start_floating(Config { garbage_delay: 20, ..Default::default() }, 4);
+    g.mode = Mode::TrainingLab { lookahead: None, search: false, mino_mode: false };
+    press(&mut g, Garbage(2), t);
+    press(&mut g, Hard, t + FRAME * 5);
+    advance(&mut g, t + FRAME * 30);
+    assert!(g.pending_garbage.is_empty());
+    press(&mut g, Undo, t + FRAME * 30);
+    assert_eq!(g.pending_garbage_lines(), 2, "the garbage was still on its way before the drop");
+    assert!(g.board[0].iter().all(|&c| c == Cell::Empty));
+    advance(&mut g, t + FRAME * 31);
+    assert!(g.pending_garbage.is_empty(), "the restored garbage should still rise");
+    assert!(g.board[1].contains(&Cell::Garbage));
+}
+
+#[test]
+fn test_attack_tables() {
+    let clear = |lines: usize, spin, combo, b2b| PlacementResult {
+        location: PieceLocation::new(Piece::T, (4, 0), Rotation::North),
+        rows: (0..lines).collect(),
+        spin,
+        perfect_clear: false,
+        combo,
+        b2b,
+        attack: 0,
+        cancelled: 0,
+        score: 0,
+        top_out: None,
+    };
+    use AttackTable::*;
+    let tsd_b2b = clear(2, SpinKind::Full, 1, 2);
+    let quad = clear(4, SpinKind::None, 3, 1);
+    let double = clear(2, SpinKind::None, 3, 0);
+    let single = clear(1, SpinKind::None, 5, 0);
+    assert_eq!(Guideline.attack(&tsd_b2b, 1), 5);
+    assert_eq!(Guideline.attack(&quad, 0), 5);
+    assert_eq!(Guideline.attack(&double, 0), 2);
+    assert_eq!(Jstris.attack(&double, 0), 2);
+    assert_eq!(Jstris.attack(&clear(2, SpinKind::None, 2, 0), 0), 1);
+    assert_eq!(Jstris.attack(&clear(2, SpinKind::Mini, 1, 1), 0), 1);
+    assert_eq!(Tetrio.attack(&tsd_b2b, 1), 5);
+    assert_eq!(Tetrio.attack(&quad, 0), 6);
+    assert_eq!(Tetrio.attack(&single, 0), 1);
+    assert_eq!(Tetrio.attack(&single, 6), 6, "breaking a b2b x5 should surge");
+    assert_eq!(Guideline.attack(&single, 6), 2, "only tetr.io has surge");
+    assert_eq!(Guideline.attack(&clear(0, SpinKind::Full, 0, 3), 3), 0);
+    let pc = PlacementResult { perfect_clear: true, ..clear(1, SpinKind::None, 1, 0) };
+    assert_eq!(Guideline.attack(&pc, 0), 10);
+}
+
+#[test]
+fn test_garbage() {
+    let (mut g, t) = start_floating(Config { garbage_delay: 20, ..Default::default() }, 4);
+    press(&mut g, Garbage(2), t);
+    advance(&mut g, t + FRAME * 19);
+    assert!(g.board[0].iter().all(|&c| c == Cell::Empty));
+    advance(&mut g, t + FRAME * 20);
+    let hole = g.board[0].iter().position(|&c| c == Cell::Empty).unwrap();
+    for row in g.board.iter().take(2) {
+        assert!(row[..10].iter().enumerate().all(|(x, &c)| (c == Cell::Empty) == (x == hole)));
+    }
+    assert!(g.board[2].iter().all(|&c| c == Cell::Empty));
+    assert_eq!(g.current.pos, (4, 10), "piece wasn't in the way");
+
+    g.current = PieceLocation::new(Piece::I, (hole as i8, 10), Rotation::East);
+    let result = press(&mut g, Hard, t + FRAME * 20).unwrap();
+    assert_eq!(result.rows, [0, 1], "garbage rows should clear");
+}
+
+#[test]
+fn test_garbage_displaces_piece() {
+    let (mut g, t) = start_floating(Config::default(), 4);
+    let delay = FRAME * g.config.garbage_delay as u32;
+    g.current.pos.1 = 0;
+    press(&mut g, Garbage(3), t);
+    advance(&mut g, t + delay);
+    assert_eq!(g.current.pos, (4, 3));
+    assert!(g.state == GameState::Running);
+
+    press(&mut g, Garbage(30), t);
+    advance(&mut g, t + delay);
+    let outcome = Some(Outcome::ToppedOut(TopOut::GarbageOut));
+    assert_eq!(g.state.outcome(), outcome, "piece can't be pushed out of the board");
+}
+
+#[test]
+fn test_garbage_cancelling() {
+    let (mut g, t) = start_floating(Config::default(), 4);
+    press(&mut g, Garbage(2), t);
+    press(&mut g, Garbage(3), t + FRAME);
+    for y in 0..2 {
+        for x in 1..10 {
+            g.board.set(x, y, Cell::Piece(Piece::L));
+        }
+    }
+    g.current = PieceLocation::new(Piece::I, (0, 10), Rotation::East);
+    let result = press(&mut g, Hard, t + FRAME * 2).unwrap();
+    assert_eq!((result.attack, result.cancelled), (1, 1));
+    assert_eq!(g.attack, 0, "cancelled lines weren't sent");
+    assert_eq!(g.pending_garbage_lines(), 4);
+    assert_eq!(g.pending_garbage.len(), 2, "the first attack should only be partly cancelled");
+}
+
+#[test]
+fn test_cheese() {
+    let mut g = Game::new(Config { gravity: None, ..Default::default() });
+    g.mode = Mode::Cheese { target_lines: 4, height: 3 };
+    g.start(Some(0), &SoundPlayer::<NullSink>::default());
+    let t = g.time + FRAME * 60;
+    advance(&mut g, t);
+    let cheese_rows = |g: &Game| g.board.iter().take_while(|r| r.contains(&Cell::Garbage)).count();
+    let hole = |g: &Game, y: usize| g.board[y].iter().position(|&c| c == Cell::Empty).unwrap();
+    assert_eq!(cheese_rows(&g), 3);
+    assert!(hole(&g, 0) != hole(&g, 1) && hole(&g, 1) != hole(&g, 2));
+    assert_eq!(g.board[0][..10].iter().filter(|&&c| c == Cell::Empty).count(), 1);
+
+    for dug in 1..=4 {
+        let x = hole(&g, 0);
+        g.board.set(x, 0, Cell::Piece(Piece::L));
+        g.current = PieceLocation::new(Piece::O, (4, 15), Rotation::North);
+        press(&mut g, Hard, t).unwrap();
+        assert_eq!(g.garbage_cleared, dug);
+        assert_eq!(g.lines_remaining(), Some(4 - dug));
+        assert_eq!(cheese_rows(&g), [3, 2, 1, 0][dug as usize - 1], "only refill what's left");
+    }
+    assert!(g.is_complete());
+    assert_eq!(g.state.outcome(), Some(Outcome::Cleared));
+}
+
+#[test]
+fn test_scoring() {
+    let clear = |lines: usize, spin, combo, b2b| PlacementResult {
+        location: PieceLocation::new(Piece::T, (4, 0), Rotation::North),
+        rows: (0..lines).collect(),
+        spin,
+        perfect_clear: false,
+        combo,
+        b2b,
+        attack: 0,
+        cancelled: 0,
+        score: 0,
+        top_out: None,
+    };
+    assert_eq!(score::score(&clear(0, SpinKind::None, 0, 0)), 0);
+    assert_eq!(score::score(&clear(4, SpinKind::None, 1, 1)), 800);
+    assert_eq!(score::score(&clear(4, SpinKind::None, 1, 2)), 1200, "b2b is worth 1.5x");
+    assert_eq!(score::score(&clear(2, SpinKind::Full, 1, 1)), 1200);
+    assert_eq!(score::score(&clear(1, SpinKind::Mini, 1, 1)), 200);
+    assert_eq!(score::score(&clear(0, SpinKind::Full, 0, 3)), 400, "b2b needs a clear");
+    assert_eq!(score::score(&clear(1, SpinKind::None, 4, 0)), 250, "3 combo is worth 150");
+    let pc = PlacementResult { perfect_clear: true, ..clear(4, SpinKind::None, 1, 2) };
+    assert_eq!(score::score(&pc), 1200 + 3200);
+}
+
+#[test]
+fn test_ultra() {
+    let mut g = Game::new(Config { gravity: None, soft_drop: 1, ..Default::default() });
+    g.mode = Mode::Ultra { duration: 2 };
+    g.start(Some(0), &SoundPlayer::<NullSink>::default());
+    assert_eq!(g.time_remaining(g.time), Some(Duration::from_secs(2)));
+    let t = g.time + FRAME * 60;
+    advance(&mut g, t);
+    assert_eq!(g.time_remaining(t + Duration::from_secs(1)), Some(Duration::from_secs(1)));
+
+    g.current = PieceLocation::new(Piece::T, (4, 10), Rotation::North);
+    press(&mut g, PressSoft, t);
+    advance(&mut g, t + FRAME * 3);
+    assert_eq!(g.score, 3 * score::SOFT_DROP);
+    press(&mut g, ReleaseSoft, t + FRAME * 3);
+    let fall = (g.current.pos.1 - g.ghost_pos().pos.1) as u32;
+    press(&mut g, Hard, t + FRAME * 3).unwrap();
+    assert_eq!(g.score, 3 * score::SOFT_DROP + fall * score::HARD_DROP);
+
+    advance(&mut g, t + Duration::from_secs(2));
+    let stats =
+        Stats { time: Duration::from_secs(2), pieces: 1, lines: 0, attack: 0, score: g.score };
+    assert!(g.state == GameState::Done(GameResult { outcome: Outcome::Cleared, stats }));
+    assert_eq!(stats.pps(), 0.5);
+    assert!(g.is_complete());
+    assert_eq!(g.time_remaining(t + Duration::from_secs(5)), Some(Duration::ZERO));
+}
+
+#[test]
+fn test_marathon() {
+    use gravity::G;
+    let mut g = Game::new(Config::default());
+    g.mode = Mode::Marathon {
+        target_lines: 20,
+        gravity: GravityCurve::Table(vec![(1, G / 2), (2, 3 * G)]),
+        lock_delay: vec![(2, 10)],
+    };
+    g.start(Some(0), &SoundPlayer::<NullSink>::default());
+    let t = g.time + FRAME * 60;
+    advance(&mut g, t);
+    assert_eq!(g.level(), Some(1));
+    let y = g.current.pos.1;
+    advance(&mut g, t + FRAME);
+    assert_eq!(g.current.pos.1, y, "half a row per frame");
+    advance(&mut g, t + FRAME * 2);
+    assert_eq!(g.current.pos.1, y - 1);
+
+    g.lines = 9;
+    for x in (0..10).filter(|x| !(3..=5).contains(x)) {
+        g.board.set(x, 0, Cell::Piece(Piece::I));
+    }
+    g.current = PieceLocation::new(Piece::T, (4, 10), Rotation::North);
+    let result = press(&mut g, Hard, t + FRAME * 2).unwrap();
+    assert_eq!(result.score, 100, "scored at the level it was cleared on");
+    assert_eq!(g.level(), Some(2));
+    let y = g.current.pos.1;
+    advance(&mut g, t + FRAME * 3);
+    assert_eq!(g.current.pos.1, y - 3, "3 rows per frame");
+    advance(&mut g, t + FRAME * 25);
+    assert_eq!(g.pieces, 2, "lock delay should be shorter on level 2");
+
+    // soft dropping at 1 row every 2 frames can't slow down 3 rows per frame
+    g.config.soft_drop = 2;
+    let t = t + FRAME * 25;
+    g.current = PieceLocation::new(Piece::T, (4, 15), Rotation::North);
+    press(&mut g, PressSoft, t);
+    advance(&mut g, t + FRAME * 2);
+    assert_eq!(g.current.pos.1, 15 - 6);
+    press(&mut g, ReleaseSoft, t + FRAME * 2);
+    assert_eq!(g.timers.iter().filter(|&&(_, ev)| ev == TimerEvent::Gravity).count(), 1);
+}
+
+#[test]
+fn test_fractional_gravity() {
+    let mut g = Game::new(Config::default());
+    g.mode = Mode::Marathon {
+        target_lines: 150,
+        gravity: GravityCurve::Table(vec![(1, 3 * gravity::G / 2)]),
+        lock_delay: vec![],
+    };
+    g.start(Some(0), &SoundPlayer::<NullSink>::default());
+    let t = g.time + FRAME * 60;
+    advance(&mut g, t);
+    let y = g.current.pos.1;
+    advance(&mut g, t + FRAME);
+    assert_eq!(g.current.pos.1, y - 1);
+    advance(&mut g, t + FRAME * 2);
+    assert_eq!(g.current.pos.1, y - 3, "1.5 rows per frame");
+}
+
+#[test]
+fn test_master() {
+    let mut g = Game::new(Config::default());
+    g.mode = Mode::Master { level: 0 };
+    g.start(Some(0), &SoundPlayer::<NullSink>::default());
+    let t = g.time + FRAME * 60;
+    advance(&mut g, t);
+    assert_eq!(g.level(), Some(0));
+    assert!(!g.is_20g());
+
+    g.mode = Mode::Master { level: 498 };
+    press(&mut g, Hard, t);
+    assert_eq!(g.level(), Some(499));
+    let t = t + FRAME * 25;
+    advance(&mut g, t);
+    press(&mut g, Hard, t);
+    assert_eq!(g.level(), Some(499), "needs a line clear to finish the section");
+
+    let t = t + FRAME * 25;
+    advance(&mut g, t);
+    g.board = Board::default();
+    for x in (0..10).filter(|x| !(3..=5).contains(x)) {
+        g.board.set(x, 0, Cell::Piece(Piece::I));
+    }
+    g.current = PieceLocation::new(Piece::T, (4, 10), Rotation::North);
+    press(&mut g, Hard, t);
+    assert_eq!(g.level(), Some(501));
+    assert!(g.is_20g());
+    // section 500 shortens the line clear delay from 40 to 25
+    advance(&mut g, t + FRAME * 50);
+    assert!(g.state == GameState::Running);
+    assert_eq!(g.current.pos, g.ghost_pos().pos, "20G pieces land as soon as they spawn");
+    press(&mut g, PressLeft, t + FRAME * 50);
+    assert_eq!(g.current.pos, g.ghost_pos().pos);
+}
+
+#[test]
+fn test_guideline_gravity() {
+    let curve = GravityCurve::Guideline;
+    assert!((curve.rows_per_frame(1) - 1.0 / 60.0).abs() < 1e-6);
+    assert!(curve.rows_per_frame(5) < 1.0);
+    assert!(curve.rows_per_frame(15) > 1.0, "level 15 is faster than 1G");
+    assert_eq!(curve.rows_per_frame(20), 20.0);
+}
+
+#[test]
+fn test_board_size() {
+    let (mut g, t) = start_floating(Config { width: 4, height: 8, ..Default::default() }, 1);
+    assert!(g.check_valid([(3, 17); 4]));
+    assert!(!g.check_valid([(4, 0); 4]), "past the right wall");
+    assert!(!g.check_valid([(0, 18); 4]), "past the buffer");
+
+    g.current = PieceLocation::new(Piece::I, (1, 10), Rotation::North);
+    let result = press(&mut g, Hard, t).unwrap();
+    assert_eq!(result.rows, [0], "4 cells fill a row");
+    assert!(result.perfect_clear);
+    assert_eq!(g.current.pos, (1, 8), "spawns above the board and drops a row");
+
+    let delay = FRAME * g.config.garbage_delay as u32;
+    press(&mut g, Garbage(1), t);
+    advance(&mut g, t + delay);
+    assert_eq!(g.board[0].iter().filter(|&&c| c == Cell::Garbage).count(), 3);
+
+    let lab = Mode::TrainingLab { lookahead: None, search: true, mino_mode: false };
+    for (width, height, enabled) in
+        [(4, 20, true), (10, 20, true), (11, 20, false), (10, 16, false), (10, 24, false)]
+    {
+        let mut g = Game::new(Config { width, height, ..Default::default() });
+        g.mode = lab.clone();
+        assert_eq!(g.search_enabled(), enabled, "the search only knows 10x20 and narrower");
+    }
+}
+
+#[test]
+fn test_bitboard() {
+    let in_sync =
+        |b: &Board| (0..50).all(|y| (0..16).all(|x| b.is_filled(x, y) == (b[y][x] != Cell::Empty)));
+    let mut b = Board::default();
+    for x in 0..10 {
+        b.set(x, 0, Cell::Piece(Piece::I));
+    }
+    b.set(3, 1, Cell::Garbage);
+    b.set(3, 4, Cell::Piece(Piece::T));
+    assert!(in_sync(&b));
+    assert_eq!(b.full_rows(10), 0b1);
+    assert_eq!(b.full_rows(4), 0b1);
+    assert_eq!(b.drop_distance([(3, 10), (4, 10), (5, 10), (4, 11)]), 5);
+    assert_eq!(b.drop_distance([(4, 10); 4]), 9);
+
+    b.clear_row(0);
+    assert!(in_sync(&b));
+    assert_eq!(b.column(3), 0b1001);
+    let mut garbage = [Cell::Garbage; 16];
+    garbage[3] = Cell::Empty;
+    b.insert_rows(2, garbage, 30);
+    assert!(in_sync(&b));
+    assert_eq!(b.column(3), 0b100100);
+    assert_eq!(b.column(0), 0b11);
+    b.set(0, 0, Cell::Empty);
+    assert!(in_sync(&b));
+    assert!(!b.is_empty_except(0b11));
+    b.clear_row(0);
+    b.clear_row(0);
+    assert!(b.is_empty_except(0b1001));
+}
+
+#[test]
+fn test_are_and_line_clear_delay() {
+    let config = Config { are: 6, line_clear_delay: 10, ..Default::default() };
+    let (mut g, t) = start_floating(config, 4);
+    for x in (0..10).filter(|x| !(3..=5).contains(x)) {
+        g.board.set(x, 0, Cell::Piece(Piece::I));
+    }
+    press(&mut g, Hard, t);
+    assert!(g.state == GameState::Are);
+    assert_eq!(g.clearing, [0]);
+    assert_eq!(g.lines, 1);
+    assert!(g.board[0][4] == Cell::Piece(Piece::T), "the row should stay until the delay is over");
+
+    let next = *g.upcomming.front().unwrap();
+    press(&mut g, Cw, t + FRAME * 5);
+    press(&mut g, Hard, t + FRAME * 6);
+    advance(&mut g, t + FRAME * 15);
+    assert!(g.state == GameState::Are);
+    assert_eq!(g.pieces, 1, "hard drop shouldn't be buffered");
+
+    advance(&mut g, t + FRAME * 16);
+    assert!(g.state == GameState::Running);
+    assert!(g.clearing.is_empty());
+    assert!(g.board[0][4] == Cell::Piece(Piece::T), "the rest of the T should have dropped down");
+    assert!(g.board[0][3] == Cell::Empty);
+    assert_eq!(g.current.piece, next);
+    assert_eq!(g.current.rot, Rotation::East, "rotation should be buffered");
+}
+
+#[test]
+fn test_are_without_clear() {
+    let (mut g, t) =
+        start_floating(Config { are: 6, line_clear_delay: 10, ..Default::default() }, 4);
+    press(&mut g, Hard, t);
+    assert!(g.state == GameState::Are);
+    advance(&mut g, t + FRAME * 6);
+    assert!(g.state == GameState::Running);
+}
+
+#[test]
+fn test_pause() {
+    let (mut g, t) = start_floating(Config::default(), 4);
+    press(&mut g, InputEvent::Garbage(2), t);
+    let (ready, _) = g.pending_garbage[0];
+    let before = g.elapsed(t);
+    press(&mut g, Pause, t);
+    assert!(!g.should_draw_board(), "sprint hides the board while paused");
+
+    let later = t + Duration::from_secs(10);
+    assert_eq!(press(&mut g, Hard, later), None);
+    assert_eq!(g.elapsed(later), before);
+    press(&mut g, Pause, later);
+    assert!(g.should_draw_board());
+    assert_eq!(g.elapsed(later), before, "paused time shouldn't count");
+    assert_eq!(g.pending_garbage[0].0, ready + Duration::from_secs(10));
+    assert!(g.timers.iter().all(|&(at, _)| at > later));
+    assert!(press(&mut g, Hard, later).is_some());
+
+    let mut replay = replay::Replay::new(g.config, g.mode.clone(), 0);
+    replay.last = Some(t);
+    replay.push(Hard, t + Duration::from_millis(100));
+    replay.pause(t + Duration::from_millis(300), later);
+    replay.push(Hard, later + Duration::from_millis(50));
+    assert_eq!(replay.pauses, [replay::PauseSpan { at: 300, length: 9700 }]);
+    let elapsed: u32 = replay.events.iter().map(|e| e.elapsed as u32).sum();
+    assert_eq!(elapsed, 350, "event timings shouldn't include the pause");
+
+    let (mut g, t) = start_floating(Config::default(), 4);
+    let sound = SoundPlayer::<NullSink>::default();
+    let mut replay = replay::Replay::new(g.config, g.mode.clone(), 0);
+    replay.last = Some(t);
+    let ms = Duration::from_millis;
+    for (at, input) in [(100, Pause), (5000, Hard), (9000, Cw), (10000, Pause), (10050, Hard)] {
+        replay.record(&mut g, input, t + ms(at), &sound);
+    }
+    let inputs: Vec<_> = replay.events.iter().map(|e| e.input).collect();
+    assert_eq!(inputs, [Pause, Pause, Hard], "inputs ignored while paused aren't recorded");
+    assert_eq!(replay.pauses, [replay::PauseSpan { at: 100, length: 9900 }]);
+}
+
+#[test]
+fn test_record_overdue() {
+    // the tui only hears about inputs, so gravity has often run out by the time one arrives
+    let sound = SoundPlayer::<NullSink>::default();
+    let config = Config { gravity: Some(1), ..Default::default() };
+    let mut g = Game::new(config);
+    let t = Instant::now();
+    g.start_at(Some(7), t, &sound);
+    let mut replay = replay::Replay::new(g.config, g.mode.clone(), 7);
+    replay.last = Some(t);
+    let ms = Duration::from_millis;
+    let inputs = [(900, Cw), (2500, PressLeft), (2600, ReleaseLeft), (4000, Hold), (5500, Hard)];
+    let mut placed = 0;
+    for (at, input) in inputs {
+        placed += replay.record(&mut g, input, t + ms(at), &sound).len();
+    }
+    assert!(placed > 2, "gravity should have locked pieces between inputs");
+    assert_eq!(placed, g.pieces);
+    let played = replay.play(&sound);
+    assert!(played.board == g.board);
+    assert_eq!((played.current, played.hold, played.pieces), (g.current, g.hold, g.pieces));
+}
+
+#[test]
+fn test_old_replay() {
+    let raw = r#"{"length": 1200, "seed": 3, "config": {}, "events": [{"elapsed": 80, "input": "Hard"}]}"#;
+    let replay: replay::Replay = serde_json::from_str(raw).unwrap();
+    assert_eq!(replay.mode, Mode::Sprint { target_lines: 40 });
+    assert!(replay.pauses.is_empty());
+}
+
+#[test]
+fn test_frame_clock() {
+    let sound = SoundPlayer::<NullSink>::default();
+    let inputs = [
+        (70, PressLeft),
+        (85, ReleaseLeft),
+        (90, Cw),
+        (150, Hard),
+        (160, PressRight),
+        (200, PressSoft),
+        (260, ReleaseSoft),
+        (261, ReleaseRight),
+        (262, Hold),
+        (300, Hard),
+    ];
+    let play = |origin| {
+        let mut clock = FrameClock::new(origin);
+        let mut g = Game::new(Config { gravity: Some(20), ..Default::default() });
+        clock.start(&mut g, 7, &sound);
+        for (frame, input) in inputs {
+            clock.step(&mut g, frame - clock.frame, &sound);
+            clock.input(&mut g, input, &sound);
+        }
+        clock.step(&mut g, 600, &sound);
+        g
+    };
+    let a = play(Instant::now());
+    let b = play(Instant::now() + Duration::from_secs(1234));
+    assert!(a.board == b.board);
+    assert_eq!((a.current, a.hold, a.pieces), (b.current, b.hold, b.pieces));
+    assert!(a.pieces > 2, "gravity should have locked pieces on its own");
+
+    let mut replay = replay::Replay::new(Config::default(), Mode::Sprint { target_lines: 40 }, 7);
+    replay.last = Some(Instant::now());
+    for (i, &(_, input)) in inputs.iter().enumerate() {
+        replay.push(input, replay.last.unwrap() + FRAME * (60 + i as u32 * 5));
+    }
+    let (a, b) = (replay.play(&sound), replay.play(&sound));
+    assert!(a.board == b.board);
+    assert_eq!((a.current, a.hold, a.pieces), (b.current, b.hold, b.pieces));
+    assert!(a.upcomming.iter().eq(b.upcomming.iter()));
+    assert!(a.pieces >= 2);
+
+    // a recorded run plays back the same way, with the inputs landing between the same timers
+    let config = Config { gravity: Some(3), das: 5, arr: 1, ..Default::default() };
+    let mut clock = FrameClock::new(Instant::now());
+    let mut g = Game::new(config);
+    clock.start(&mut g, 7, &sound);
+    let mut replay = replay::Replay::new(config, g.mode.clone(), 7);
+    replay.last = Some(clock.now());
+    // an input every frame, so any rounding in the timings would add up to whole frames
+    let moves = [PressLeft, ReleaseLeft, Cw, PressRight, ReleaseRight, Ccw, PressSoft, ReleaseSoft];
+    for (frame, &input) in (60..300).zip(moves.iter().cycle()) {
+        clock.step(&mut g, frame - clock.frame, &sound);
+        replay.push(input, clock.now());
+        clock.input(&mut g, input, &sound);
+    }
+    let played = replay.play(&sound);
+    assert!(played.board == g.board);
+    assert_eq!((played.current, played.hold, played.pieces), (g.current, g.hold, g.pieces));
+}
+
+fn sequence(kind: RandomizerKind, seed: u64, len: usize) -> Vec<Piece> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut randomizer = kind.build();
+    randomizer.seed(seed);
+    let mut pieces = vec![];
+    while pieces.len() < len {
+        pieces.extend(randomizer.next_batch(&mut rng));
+    }
+    pieces.truncate(len);
+    pieces
+}
+
+#[test]
+fn test_randomizers() {
+    use {Piece::*, RandomizerKind::*};
+    assert_eq!(sequence(Bag7, 0, 14), [J, I, L, O, Z, S, T, Z, J, L, S, O, I, T]);
+    assert_eq!(sequence(Bag14, 0, 14), [J, I, I, O, Z, Z, O, L, J, S, T, L, T, S]);
+    assert_eq!(sequence(Bag7Plus1, 0, 16), [Z, J, L, S, O, T, T, I, T, I, S, L, J, O, Z, T]);
+    assert_eq!(sequence(Random, 0, 8), [T, T, O, T, T, I, S, S]);
+    assert_eq!(sequence(Tgm4Roll, 0, 8), [T, O, I, S, Z, J, T, I]);
+    assert_eq!(sequence(Tgm6Roll, 0, 16), [T, O, I, S, Z, J, T, I, S, O, Z, T, J, I, I, O]);
+    assert_eq!(sequence(Nes, 0, 8), [I, L, S, I, T, L, S, I]);
+
+    for seed in 0..20 {
+        for bag in sequence(Bag7, seed, 70).chunks(7) {
+            assert_eq!(bag.iter().copied().collect::<BTreeSet<_>>().len(), 7);
+        }
+        for bag in sequence(Bag14, seed, 70).chunks(14) {
+            let mut bag = bag.to_vec();
+            bag.sort();
+            assert_eq!(bag, [I, I, J, J, L, L, O, O, S, S, T, T, Z, Z]);
+        }
+        assert!(!matches!(sequence(Tgm6Roll, seed, 1)[0], S | Z | O));
+    }
+
+    // the same seed gives the same queue, and only starts with an S or Z when allowed to
+    let sound = SoundPlayer::<NullSink>::default();
+    let queue = |config, seed| {
+        let mut g = Game::new(config);
+        g.start(Some(seed), &sound);
+        g.upcomming.iter().copied().collect::<Vec<_>>()
+    };
+    let tgm = Config { randomizer: Tgm4Roll, ..Default::default() };
+    assert_eq!(queue(tgm, 3), queue(tgm, 3));
+    let starts = |no_sz_start, mode: &Mode| {
+        (0..20).any(|seed| {
+            let mut g = Game::new(Config { no_sz_start, ..Default::default() });
+            g.mode = mode.clone();
+            g.start(Some(seed), &sound);
+            matches!(g.upcomming[0], S | Z)
+        })
+    };
+    let (sprint, cheese) = (Mode::default(), Mode::Cheese { target_lines: 10, height: 10 });
+    assert!(starts(Some(false), &sprint));
+    assert!(!starts(Some(true), &cheese));
+    assert!(!starts(None, &sprint), "sprint rerolls by default");
+    assert!(starts(None, &cheese), "other modes keep the first bag by default");
+}
+
+#[test]
+fn test_jstris() {
+    use {Piece::*, randomizer::*};
+    // these floats and both sequences were checked against Baagøe's reference Alea in javascript,
+    // drawing from the bag the same way `Jstris::next_batch` does
+    let mut alea = Alea::new("hello.");
+    let expected =
+        [0.4783254903741181, 0.8297006865032017, 0.46924330526962876, 0.2505575136747211];
+    assert_eq!(expected.map(|_| alea.next_f64()), expected);
+
+    let seed = seed_from_str("abc123").unwrap();
+    assert_eq!(seed_to_string(seed), "abc123");
+    assert_eq!(seed_from_str("too long!"), None);
+    assert_eq!(
+        sequence(RandomizerKind::Jstris, seed, 14),
+        [S, Z, T, O, I, J, L, S, I, T, Z, O, L, J]
+    );
+    let hello = seed_from_str("hello").unwrap();
+    assert_eq!(
+        sequence(RandomizerKind::Jstris, hello, 14),
+        [Z, T, J, L, I, S, O, L, T, J, O, I, S, Z]
+    );
+
+    // jstris doesn't reroll bags that start with an S or Z
+    let mut g = Game::new(Config { randomizer: RandomizerKind::Jstris, ..Default::default() });
+    g.start(Some(seed), &SoundPlayer::<NullSink>::default());
+    assert_eq!(g.upcomming.front(), Some(&S));
+}
@@ -14,7 +14,10 @@ use anyhow::{Result, anyhow};
 use log::error;
 use ringbuffer::RingBuffer;
 use termios::*;
-use tetris::{BG_COLOR, Color, Game, GameState, LOST_COLOR, Mode, Piece, PieceLocation, Rotation};
+use tetris::{
+    ATTACK_COLOR, BG_COLOR, CLEAR_COLOR, Color, DONE_COLOR, GARBAGE_COLOR, Game, GameState,
+    LOST_COLOR, Outcome, Piece, PieceLocation, Rotation,
+};
 use web_time::Instant;
 
 macro_rules! csi {
@@ -47,35 +50,37 @@ pub fn draw(width: i16, height: i16, game: &Game) -> Result<()> {
     set_color(o, BG_COLOR)?;
     write!(o, csi!("2J"))?;
 
-    // Origin is top left of drawing area
-    let (ox, oy) = (width / 2 - 19, height / 2 - 11);
+    // Origin is top left of drawing area, the board is 2 characters per cell with 2 rows above it
+    let (bw, bh) = (game.config.width as i16, game.config.height as i16);
+    let (ox, oy) = (width / 2 - 9 - bw, height / 2 - 1 - bh / 2);
+    let (right, bottom) = (ox + 12 + 2 * bw, oy + 2 + bh);
     draw_board(o, game, (ox + 10, oy))?;
-    if let Some(hold) = game.hold {
-        draw_piece(o, hold, (ox, oy + 4))?;
+    if game.should_draw_hold() {
+        for (i, &hold) in game.hold.iter().enumerate() {
+            draw_piece(o, hold, (ox, oy + 4 + 3 * i as i16))?;
+        }
     }
-    for i in 0..5 {
+    // the clear text goes below however many hold slots there are
+    let text_top = oy + 5 + 3 * game.hold_slots().max(1) as i16;
+    let preview = if game.should_draw_queue() { game.preview() } else { 0 };
+    for i in 0..preview {
         draw_piece(
             o,
             *game.upcomming.get(i).ok_or(anyhow!("piece queue empty"))?,
-            (ox + 32, oy + 4 + 3 * i as i16),
+            (right, oy + 4 + 3 * i as i16),
         )?;
     }
     let text_color = (255, 255, 255);
-    if let Mode::Sprint { target_lines: target } = game.mode {
+    if let Some(remaining) = game.lines_remaining() {
         set_color(o, BG_COLOR)?;
-        draw_text(
-            o,
-            (ox + 34, oy + 20),
-            text_color,
-            &(target.saturating_sub(game.lines)).to_string(),
-        )?;
+        draw_text(o, (right + 2, bottom - 2), text_color, &remaining.to_string())?;
     }
     let now = Instant::now();
-    let time = match game.state {
-        GameState::Startup => Duration::ZERO,
-        GameState::Running => now.duration_since(game.start_time.unwrap_or(now)),
-        GameState::Done => game.time.duration_since(game.start_time.unwrap_or(now)),
-    };
+    let elapsed = game.elapsed(now);
+    let apm =
+        if elapsed.is_zero() { 0.0 } else { game.attack as f64 * 60.0 / elapsed.as_secs_f64() };
+    // timed modes count down instead
+    let time = game.time_remaining(now).unwrap_or(elapsed);
     let mins = time.as_secs() / 60;
     let secs = time.as_secs() % 60;
     let decis = time.as_millis() % 1000 / 100;
@@ -85,7 +90,51 @@ pub fn draw(width: i16, height: i16, game: &Game) -> Result<()> {
         format!("{secs:2}.{decis:01} ")
     };
     set_color(o, BG_COLOR)?;
-    draw_text(o, (ox + 1, oy + 20), text_color, &time)?;
+    draw_text(o, (ox + 1, bottom - 2), text_color, &time)?;
+    if game.paused.is_some() {
+        draw_text(o, (ox + 1, bottom - 3), text_color, "paused")?;
+    }
+    // what killed you takes the place of the last clear
+    let message = match game.state.outcome() {
+        Some(Outcome::ToppedOut(reason)) => Some(reason.to_string()),
+        _ => game.last_placement.as_ref().map(ToString::to_string),
+    };
+    if let Some(message) = message {
+        // one word per line, right aligned against the board
+        for (i, word) in message.split(' ').enumerate() {
+            draw_text(o, (ox + 9 - word.len() as i16, text_top + i as i16), text_color, word)?;
+        }
+    }
+    // the stats go under the clear text, which takes up to 5 lines
+    let stats_top = text_top + 5;
+    if game.combo > 1 {
+        let combo = format!("{} combo", game.combo - 1);
+        draw_text(o, (ox + 9 - combo.len() as i16, stats_top + 1), text_color, &combo)?;
+    }
+    if game.b2b > 1 {
+        let b2b = format!("b2b x{}", game.b2b - 1);
+        draw_text(o, (ox + 9 - b2b.len() as i16, stats_top + 2), text_color, &b2b)?;
+    }
+    if let Some(level) = game.level() {
+        let level = format!("lvl {level}");
+        draw_text(o, (ox + 9 - level.len() as i16, stats_top), text_color, &level)?;
+    }
+    let score = format!("pts {}", game.score);
+    draw_text(o, (ox + 9 - score.len() as i16, stats_top + 3), text_color, &score)?;
+    let attack = format!("atk {}", game.attack);
+    draw_text(o, (ox + 9 - attack.len() as i16, stats_top + 4), text_color, &attack)?;
+    let apm = format!("apm {apm:.1}");
+    draw_text(o, (ox + 9 - apm.len() as i16, stats_top + 5), text_color, &apm)?;
+    let lines = format!("lines {}", game.lines);
+    draw_text(o, (ox + 9 - lines.len() as i16, stats_top + 6), text_color, &lines)?;
+    // gauge next to the board with incoming garbage, topped by what the last placement sent
+    let incoming = game.pending_garbage_lines().min(bh as u16) as i16;
+    let sent = game.last_placement.as_ref().map_or(0, |p| p.attack - p.cancelled) as i16;
+    for i in 0..(incoming + sent).min(bh) {
+        set_color(o, if i < incoming { GARBAGE_COLOR } else { ATTACK_COLOR })?;
+        move_cursor(o, (ox + 9, bottom - i))?;
+        write!(o, " ")?;
+    }
     draw_spins(o, game, (ox, oy))?;
     Ok(o.flush()?)
 }
@@ -106,26 +155,27 @@ fn draw_spins(o: &mut StdoutLock, game: &Game, (ox, oy): (i16, i16)) -> Result<(
             log::info!("hold: {:?}", game.hold);
             log::info!("current: {:?}", game.current);
             log::info!("upcoming: {:?}", game.upcomming);
-            if game.hold.is_none() {
+            if game.hold_slots() > 0 && game.hold.is_empty() {
                 game.hold();
                 game.can_hold = true;
             }
-            if Some(loc.piece) == game.hold.map(Into::into) {
+            if Some(loc.piece) == game.hold.first().map(|&piece| piece.into()) {
                 game.hold();
-            } else {
-                assert_eq!(loc.piece, game.current.piece.into());
+            } else if loc.piece != game.current.piece.into() {
+                log::warn!("solution places {:?} but that piece isn't available", loc.piece);
+                break;
             }
             game.current.rot = loc.rotation.into();
             game.current.pos = (loc.x, loc.y);
             log::info!("current pre-lock: {:?}", game.current);
-            draw_board(o, &game, (ox + 40, oy))?;
+            draw_board(o, &game, (ox + 20 + 2 * game.config.width as i16, oy))?;
             o.flush()?;
             std::thread::sleep(Duration::from_millis(400));
             if ind == last || (loc.spun && placement_info.lines_cleared > 0) {
                 break;
             }
             game.lock();
-            draw_board(o, &game, (ox + 40, oy))?;
+            draw_board(o, &game, (ox + 20 + 2 * game.config.width as i16, oy))?;
             o.flush()?;
             std::thread::sleep(Duration::from_millis(400));
         }
@@ -174,19 +224,33 @@ fn draw_board(o: &mut StdoutLock, g: &Game, origin: (i16, i16)) -> Result<()> {
     set_color(o, BG_COLOR)?;
     write!(o, csi!("2J"))?;
     move_cursor(o, (ox, oy))?;
-    for y in 0..22i8 {
+    let (width, height) = (g.config.width as i8, g.config.height as i8);
+    let visible = g.should_draw_board();
+    // the stack turns green for a win and grey for a loss
+    let done_color = match g.state.outcome() {
+        Some(Outcome::Cleared) => Some(DONE_COLOR),
+        Some(_) => Some(LOST_COLOR),
+        None => None,
+    };
+    for y in 0..height + 2 {
         move_cursor(o, (ox, oy + y as i16 + 1))?;
-        for x in 0..10i8 {
-            let y = 21 - y;
+        for x in 0..width {
+            let y = height + 1 - y;
             let mut color = g.board[y as usize][x as usize].color();
-            if g.state == GameState::Done && color != Default::default() {
-                color = LOST_COLOR;
+            if !visible {
+                color = BG_COLOR;
+            } else if let Some(done_color) = done_color
+                && color != Default::default()
+            {
+                color = done_color;
+            } else if g.clearing.contains(&(y as usize)) {
+                color = CLEAR_COLOR;
             } else if current_pos.contains(&(x, y)) && g.state == GameState::Running {
                 color = g.current.piece.color()
             } else if g.config.ghost && ghost.contains(&(x, y)) && g.state == GameState::Running {
                 let (r, g, b) = g.current.piece.color();
                 color = (r / 3, g / 3, b / 3);
-            } else if y > 19 {
+            } else if y >= height {
                 color = BG_COLOR;
             }
             set_color(o, color)?;
@@ -73,6 +73,8 @@ impl EventLoop {
                 (('9', 0, true), ShowSolution(9)),
                 (('0', 0, true), ShowSolution(0)),
                 (('u', 0, true), Undo),
+                (('y', 0, true), Redo),
+                (('p', 0, true), Pause),
             ]
             .into_iter()
             .map(|(k, i)| (k.into(), i))
@@ -81,6 +83,7 @@ impl EventLoop {
             loop {
                 let n = stdin.read(&mut buf).unwrap();
                 log::trace!("{:?}", parse_kitty_key(&buf[..n]));
+                #[allow(clippy::collapsible_if)]
                 if let Ok(k) = parse_kitty_key(&buf[..n]) {
                     if let Some(&ev) = keymap.get(&k) {
                         tx.send(ev).unwrap();
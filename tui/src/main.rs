@@ -12,16 +12,16 @@ use std::{
 };
 
 use clap::{
-    Parser,
+    Parser, ValueEnum,
     builder::{Styles, styling::AnsiColor::*},
 };
 use directories::ProjectDirs;
 use graphics::RawMode;
 use input::EventLoop;
 use log::{LevelFilter, debug, error};
-use rand::prelude::*;
 use tetris::{
-    Event, Game, GameState, InputEvent, Mode,
+    Game, GameResult, GameState, GravityCurve, InputEvent, Mode, Outcome,
+    randomizer::{random_seed, seed_from_str, seed_to_string},
     replay::Replay,
     sound::{Sink, SoundPlayer},
 };
@@ -35,7 +35,20 @@ struct Args {
     #[arg(short, long)]
     lines: Option<NonZeroU16>,
 
-    /// Enable practice mode (no line count)
+    /// Set the time limit in seconds for ultra
+    #[arg(short, long)]
+    duration: Option<NonZeroU16>,
+
+    /// Which mode to play
+    #[arg(short, long, value_enum, default_value_t = ModeArg::Sprint)]
+    mode: ModeArg,
+
+    /// Seed for the piece sequence, up to 8 characters. The jstris randomizer gives the same pieces
+    /// as jstris does for the same seed
+    #[arg(short, long, value_parser = parse_seed)]
+    seed: Option<u64>,
+
+    /// Enable practice mode (no line count), same as `--mode practice`
     #[arg(short, long)]
     practice: bool,
 
@@ -55,6 +68,26 @@ struct Args {
     verbose: u8,
 }
 
+fn parse_seed(s: &str) -> Result<u64, String> {
+    seed_from_str(s).ok_or_else(|| "seeds are up to 8 ascii characters".to_owned())
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ModeArg {
+    /// Clear lines as fast as possible
+    Sprint,
+    /// Dig through garbage as fast as possible
+    Cheese,
+    /// Clear lines while the game speeds up every 10 lines
+    Marathon,
+    /// Survive until level 999 as the game speeds up to 20G
+    Master,
+    /// Score as many points as possible in a time limit
+    Ultra,
+    /// Training lab with spin suggestions
+    Practice,
+}
+
 fn main() {
     // TODO: on first run create config file and print help, maybe open config in editor?
     // TODO: add mode to generate bindings for config with input prompts accepting raw mode keypresses
@@ -77,17 +110,25 @@ fn main() {
     let _mode = RawMode::enter();
     let input = EventLoop::start(keys);
     let mut game = Game::new(config);
-    game.mode = if args.practice {
-        Mode::TrainingLab { search: true, lookahead: None, mino_mode: false }
-    } else {
-        Mode::Sprint { target_lines: args.lines.map(u16::from).unwrap_or(40) }
+    let lines = args.lines.map(u16::from);
+    game.mode = match if args.practice { ModeArg::Practice } else { args.mode } {
+        ModeArg::Sprint => Mode::Sprint { target_lines: lines.unwrap_or(40) },
+        ModeArg::Cheese => Mode::Cheese { target_lines: lines.unwrap_or(10), height: 10 },
+        ModeArg::Marathon => Mode::Marathon {
+            target_lines: lines.unwrap_or(150),
+            gravity: GravityCurve::Guideline,
+            lock_delay: vec![],
+        },
+        ModeArg::Master => Mode::Master { level: 0 },
+        ModeArg::Ultra => Mode::Ultra { duration: args.duration.map_or(180, u16::from) },
+        ModeArg::Practice => Mode::TrainingLab { search: true, lookahead: None, mino_mode: false },
     };
     let replay_dir = args.replay_dir.unwrap_or_else(|| {
         let d = dirs.data_dir().join("replays");
         fs::create_dir_all(&d).ok();
         d
     });
-    while run_game(&mut game, &input, &player, &replay_dir) {}
+    while run_game(&mut game, args.seed, &input, &player, &replay_dir) {}
 }
 
 // plan for how to integrate tetrizz search algorithm
@@ -103,6 +144,7 @@ fn main() {
 
 fn run_game(
     game: &mut Game,
+    seed: Option<u64>,
     input: &EventLoop,
     player: &SoundPlayer<impl Sink>,
     replay_dir: &Path,
@@ -124,12 +166,13 @@ fn run_game(
         458.29822,
     );
     let (width, height) = get_size();
-    if width < 40 || height < 22 {
+    if width < 20 + 2 * game.config.width as u16 || height < 2 + game.config.height as u16 {
         panic!("screen too small");
     }
 
-    let seed = rand::rng().random();
-    let mut replay = Replay::new(game.config, seed);
+    let seed = seed.unwrap_or_else(|| random_seed(&mut rand::rng()));
+    log::info!("seed: {}", seed_to_string(seed));
+    let mut replay = Replay::new(game.config, game.mode.clone(), seed);
     game.start(Some(seed), player);
     replay.start();
 
@@ -137,7 +180,8 @@ fn run_game(
     let mut new_piece = false;
 
     let done = loop {
-        if game.mode.search_enabled() && new_piece {
+        // wait for the next piece to actually spawn if there's an entry delay
+        if game.search_enabled() && new_piece && game.state == GameState::Running {
             // call search algorithm
             log::info!("upcoming: {:?}", game.upcomming);
             log::info!("hold: {:?}", game.hold);
@@ -146,16 +190,19 @@ fn run_game(
             log::info!("queue: {queue:?}");
             log::info!("hold: {:?}", tetrizz_game.hold);
             let search_loc = tetrizz::movegen::movegen(&tetrizz_game, queue[0]);
+            let depth = queue.len().min(7);
             let heap = tetrizz::beam_search::search_results(
                 &tetrizz_game,
                 &search_loc,
                 queue,
                 eval,
-                7,
+                depth,
                 3000,
             );
             let mut spins = vec![];
-            for node in heap.iter() {
+            let usable =
+                heap.iter().filter(|node| game.can_follow(node.moves.iter().map(|m| m.0.piece)));
+            for node in usable {
                 for (m, placement_info) in node.moves.iter() {
                     if placement_info.lines_cleared > 0 {
                         if m.spun {
@@ -170,45 +217,33 @@ fn run_game(
             game.spins = spins;
             new_piece = false;
         }
-        use GameState::*;
         use InputEvent::*;
         use mpsc::RecvTimeoutError::*;
         let now = Instant::now();
-        let redraw_timeout = Duration::from_millis(if game.state == Done { 10000 } else { 100 });
+        let done = game.state.outcome().is_some();
+        let redraw_timeout = Duration::from_millis(if done { 10000 } else { 100 });
         let deadline = game
             .timers
             .front()
+            .filter(|_| game.paused.is_none())
             .map(|&(t, _)| t)
             .unwrap_or(now + redraw_timeout)
             .min(now + redraw_timeout);
         match input.events.recv_timeout(deadline - now) {
-            Ok(Restart) => break true,
-            Ok(Quit) => break false,
+            Ok(Restart) => {
+                game.abort(Instant::now());
+                break true;
+            }
+            Ok(Quit) => {
+                game.abort(Instant::now());
+                break false;
+            }
             Ok(input_event) => {
-                if game.state == Running
-                    || game.state == Startup
-                        && matches!(
-                            input_event,
-                            PressLeft | PressRight | ReleaseLeft | ReleaseRight
-                        )
-                {
-                    debug!(target: "input", "{input_event:?}");
-                    let t = Instant::now();
-                    replay.push(input_event, t);
-                    new_piece |= game.handle(Event::Input(input_event), t, player)
-                }
+                debug!(target: "input", "{input_event:?}");
+                new_piece |= !replay.record(game, input_event, Instant::now(), player).is_empty();
             }
             Err(Timeout) => {
-                if game.state != Done
-                    && let Some(&(t, timer_event)) = game.timers.front()
-                {
-                    let now = Instant::now();
-                    if t < now {
-                        game.timers.pop_front();
-                        debug!(target: "timer","{timer_event:?}");
-                        new_piece |= game.handle(Event::Timer(timer_event), now, player);
-                    }
-                }
+                new_piece |= !game.advance(Instant::now(), player).is_empty();
             }
             Err(Disconnected) => {
                 error!("input thread died unexpectedly");
@@ -221,8 +256,8 @@ fn run_game(
         graphics::draw(width as i16, height as i16, game).unwrap();
     };
 
-    if game.state == GameState::Done && game.mode.is_complete(game.lines) {
-        replay.length = (game.end_time.unwrap() - game.start_time.unwrap()).as_millis() as u32;
+    if let GameState::Done(GameResult { outcome: Outcome::Cleared, stats }) = game.state {
+        replay.length = stats.time.as_millis() as u32;
         save_replay(&mut replay, replay_dir);
     }
     done
@@ -1,11 +1,13 @@
 use std::{fs, path::Path, str::FromStr};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, ensure};
 use directories::ProjectDirs;
 use kdl::{KdlDocument, KdlEntry, KdlNode, KdlValue};
 use rodio::Source;
 use tetris::{
-    Config,
+    AttackTable, Config, FlipKicks, HoldRule, LockOut, RandomizerKind, RotationSystem, SpinRule,
+    board::{MAX_HEIGHT, MAX_WIDTH},
+    game::{MAX_HOLD_SLOTS, MAX_PREVIEW},
     sound::{Action, Clear, Meta, Sink, SoundPlayer},
 };
 
@@ -55,7 +57,74 @@ pub fn load(
             get_config("timeout", config_node)? as u16,
         ),
         ghost: config_node.get_arg("ghost").and_then(KdlValue::as_bool).unwrap_or(true),
+        are: get_config("are", config_node).map_or(0, |i| i as u16),
+        line_clear_delay: get_config("line-clear-delay", config_node).map_or(0, |i| i as u16),
+        spin_rule: config_node
+            .get_arg("spin-rule")
+            .and_then(KdlValue::as_string)
+            .map(|s| SpinRule::from_str(s).context(format!("invalid spin rule '{s}'")))
+            .transpose()?
+            .unwrap_or_default(),
+        attack_table: config_node
+            .get_arg("attack-table")
+            .and_then(KdlValue::as_string)
+            .map(|s| AttackTable::from_str(s).context(format!("invalid attack table '{s}'")))
+            .transpose()?
+            .unwrap_or_default(),
+        garbage_delay: get_config("garbage-delay", config_node).map_or(20, |i| i as u16),
+        width: get_config("width", config_node).map_or(10, |i| i as u8),
+        height: get_config("height", config_node).map_or(20, |i| i as u8),
+        randomizer: config_node
+            .get_arg("randomizer")
+            .and_then(KdlValue::as_string)
+            .map(|s| RandomizerKind::from_str(s).context(format!("invalid randomizer '{s}'")))
+            .transpose()?
+            .unwrap_or_default(),
+        no_sz_start: config_node.get_arg("no-sz-start").and_then(KdlValue::as_bool),
+        rotation_system: config_node
+            .get_arg("rotation-system")
+            .and_then(KdlValue::as_string)
+            .map(|s| RotationSystem::from_str(s).context(format!("invalid rotation system '{s}'")))
+            .transpose()?
+            .unwrap_or_default(),
+        flip_kicks: config_node
+            .get_arg("flip-kicks")
+            .and_then(KdlValue::as_string)
+            .map(|s| FlipKicks::from_str(s).context(format!("invalid flip kicks '{s}'")))
+            .transpose()?
+            .unwrap_or_default(),
+        lock_out: config_node
+            .get_arg("lock-out")
+            .and_then(KdlValue::as_string)
+            .map(|s| LockOut::from_str(s).context(format!("invalid lock out rule '{s}'")))
+            .transpose()?
+            .unwrap_or_default(),
+        hold: config_node
+            .get_arg("hold")
+            .and_then(KdlValue::as_string)
+            .map(|s| HoldRule::from_str(s).context(format!("invalid hold rule '{s}'")))
+            .transpose()?
+            .unwrap_or_default(),
+        hold_slots: get_config("hold-slots", config_node).map_or(1, |i| i as u8),
+        preview: get_config("preview", config_node).map_or(5, |i| i as u8),
     };
+    ensure!((4..=MAX_WIDTH as u8).contains(&config.width), "width must be from 4 to {MAX_WIDTH}");
+    ensure!(
+        (4..=MAX_HEIGHT as u8).contains(&config.height),
+        "height must be from 4 to {MAX_HEIGHT}"
+    );
+    ensure!(
+        (1..=MAX_HOLD_SLOTS).contains(&config.hold_slots),
+        "hold-slots must be from 1 to {MAX_HOLD_SLOTS}"
+    );
+    // each extra hold slot pushes the clear text and stats 3 rows further down beside the board
+    let min_height = 17 + 3 * config.hold_slots;
+    ensure!(
+        config.hold_slots == 1 || config.height >= min_height,
+        "{} hold slots need a height of at least {min_height}",
+        config.hold_slots
+    );
+    ensure!(config.preview <= MAX_PREVIEW, "preview must be from 0 to {MAX_PREVIEW}");
     let bindings = Bindings {
         left: get_binding("left", bindings_node)?,
         right: get_binding("right", bindings_node)?,
@@ -1,12 +1,13 @@
 use image::{ImageFormat, imageops::FilterType};
 use ringbuffer::RingBuffer;
-use tetris::{Cell, Game, GameState, Piece, PieceLocation, Rotation};
+use tetris::{
+    ATTACK_COLOR, CLEAR_COLOR, Cell, GARBAGE_COLOR, Game, GameState, Piece, PieceLocation, Rotation,
+};
 use ultraviolet::DVec3;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{
-    Blob, CanvasRenderingContext2d, HtmlCanvasElement, ImageBitmap, ImageData,
-    Response,
+    Blob, CanvasRenderingContext2d, HtmlCanvasElement, ImageBitmap, ImageData, Response,
     js_sys::{Uint8Array, Uint8ClampedArray},
 };
 
@@ -38,26 +39,39 @@ pub fn draw_board(
     skin: &Skin,
     t: f64,
 ) -> Result<(), JsValue> {
+    let (width, height) = (game.config.width as usize, game.config.height as usize);
+    fit_canvas(canvas, width * SIZE + 2, height * SIZE);
     let cx = canvas.get_context("2d")?.unwrap().dyn_into::<CanvasRenderingContext2d>()?;
     cx.clear_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
     if !game.should_draw_board() {
         return Ok(());
     }
     let border_width = 1.0;
-    for y in 0..20 {
-        for x in 0..10 {
+    for y in 0..height {
+        for x in 0..width {
             if let Some(mut sprite) = skindex(game.board[y][x]).map(|i| &skin[i]) {
-                if game.state != GameState::Running {
+                if game.state.outcome().is_some() {
                     sprite = &skin[0];
                 }
                 cx.draw_image_with_image_bitmap(
                     sprite,
                     (x * SIZE) as f64 + border_width,
-                    ((19 - y) * SIZE) as f64 + border_width,
+                    ((height - 1 - y) * SIZE) as f64 + border_width,
                 )?;
             }
         }
     }
+    // rows waiting on the line clear delay get flashed
+    let (r, g, b) = CLEAR_COLOR;
+    cx.set_fill_style_str(&format!("rgb({r}, {g}, {b})"));
+    for &y in game.clearing.iter().filter(|&&y| y < height) {
+        cx.fill_rect(
+            border_width,
+            ((height - 1 - y) * SIZE) as f64 + border_width,
+            (width * SIZE) as f64,
+            SIZE as f64,
+        );
+    }
     // only draw ghost and current piece while game is running
     if game.state != GameState::Running {
         return Ok(());
@@ -66,15 +80,16 @@ pub fn draw_board(
     let PieceLocation { piece, pos: (x, y), rot } = game.current;
     cx.set_global_alpha(0.25); // TODO: slider
     let ghost = game.ghost_pos();
+    let top = height as i8 - 1;
     let origin = (
         (ghost.pos.0 as f64 * SIZE as f64 + border_width),
-        ((19 - ghost.pos.1) as f64 * SIZE as f64 + border_width),
+        ((top - ghost.pos.1) as f64 * SIZE as f64 + border_width),
     );
     draw_piece(canvas, skin, piece, rot, origin, game.draw_only_mino())?;
     cx.set_global_alpha(1.0);
 
     let origin =
-        (x as f64 * SIZE as f64 + border_width, (19 - y) as f64 * SIZE as f64 + border_width);
+        (x as f64 * SIZE as f64 + border_width, (top - y) as f64 * SIZE as f64 + border_width);
     draw_piece(canvas, skin, piece, rot, origin, game.draw_only_mino())?;
 
     // rainbow border cuz why not :3
@@ -86,6 +101,33 @@ pub fn draw_board(
     Ok(())
 }
 
+/// Bar next to the board with incoming garbage, topped by what the last placement sent
+pub fn draw_gauge(game: &Game, canvas: &HtmlCanvasElement) -> Result<(), JsValue> {
+    let height = game.config.height as usize;
+    fit_canvas(canvas, canvas.width() as usize, height * SIZE);
+    let cx = canvas.get_context("2d")?.unwrap().dyn_into::<CanvasRenderingContext2d>()?;
+    cx.clear_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
+    let incoming = game.pending_garbage_lines().min(height as u16) as usize;
+    let sent = game.last_placement.as_ref().map_or(0, |p| p.attack - p.cancelled) as usize;
+    let sent = sent.min(height - incoming);
+    let bottom = canvas.height() as f64;
+    for (color, offset, lines) in [(GARBAGE_COLOR, 0, incoming), (ATTACK_COLOR, incoming, sent)] {
+        let (r, g, b) = color;
+        cx.set_fill_style_str(&format!("rgb({r}, {g}, {b})"));
+        let (y, height) = (((offset + lines) * SIZE) as f64, (lines * SIZE) as f64);
+        cx.fill_rect(0.0, bottom - y, canvas.width() as f64, height);
+    }
+    Ok(())
+}
+
+/// Resizes the canvas to match the board size, which also clears it
+fn fit_canvas(canvas: &HtmlCanvasElement, width: usize, height: usize) {
+    if canvas.width() != width as u32 || canvas.height() != height as u32 {
+        canvas.set_width(width as u32);
+        canvas.set_height(height as u32);
+    }
+}
+
 fn draw_piece(
     canvas: &HtmlCanvasElement,
     skin: &Skin,
@@ -109,6 +151,10 @@ fn draw_piece(
 }
 
 pub fn draw_hold(game: &Game, canvas: &HtmlCanvasElement, skin: &Skin) -> Result<(), JsValue> {
+    // one slot per piece that can be held, so there's nothing to see with hold turned off
+    let slots = game.hold_slots();
+    let height = if slots == 0 { 0 } else { (1 + 3 * slots) * SIZE };
+    fit_canvas(canvas, canvas.width() as usize, height);
     let cx = canvas.get_context("2d")?.unwrap().dyn_into::<CanvasRenderingContext2d>()?;
     // cx.set_fill_style_str("rgb(17, 17, 17)");
     // cx.fill_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
@@ -116,25 +162,22 @@ pub fn draw_hold(game: &Game, canvas: &HtmlCanvasElement, skin: &Skin) -> Result
     if !game.should_draw_hold() {
         return Ok(());
     }
-    if let Some(piece) = game.hold {
+    for (i, &piece) in game.hold.iter().enumerate() {
         draw_piece(
             canvas,
             skin,
             piece,
             Rotation::North,
-            (SIZE as _, SIZE as _),
+            (SIZE as f64, ((1 + 3 * i) * SIZE) as f64),
             game.draw_only_mino(),
         )?;
     }
     Ok(())
 }
 
-pub fn draw_queue(
-    game: &Game,
-    canvas: &HtmlCanvasElement,
-    skin: &Skin,
-    depth: usize,
-) -> Result<(), JsValue> {
+pub fn draw_queue(game: &Game, canvas: &HtmlCanvasElement, skin: &Skin) -> Result<(), JsValue> {
+    let depth = game.preview();
+    fit_canvas(canvas, canvas.width() as usize, 3 * depth * SIZE);
     let cx = canvas.get_context("2d")?.unwrap().dyn_into::<CanvasRenderingContext2d>()?;
     // cx.set_fill_style_str("rgb(17, 17, 17)");
     // cx.fill_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
@@ -35,6 +35,8 @@ pub fn init_input_handlers(events: mpsc::Sender<Event>) -> Result<(), JsValue> {
         ("hold", "a", Hold, None),
         ("restart", "r", Restart, None),
         ("undo", "u", Undo, None),
+        ("redo", "y", Redo, None),
+        ("pause", "p", Pause, None),
     ];
     let bind_all =
         doc.get_element_by_id("bind-all").unwrap().dyn_into::<HtmlButtonElement>().unwrap();
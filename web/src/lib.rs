@@ -5,11 +5,14 @@ mod input;
 use std::sync::mpsc::{Receiver, channel};
 
 use log::info;
+use tetris::board::{MAX_HEIGHT, MAX_WIDTH};
+use tetris::game::{MAX_HOLD_SLOTS, MAX_PREVIEW};
+use tetris::randomizer::seed_from_str;
 use tetris::sound::{NullSink, Sink, SoundPlayer};
-use tetris::{Config, Event, Game, GameState};
+use tetris::{Config, Event, Game, GameResult, GameState, GravityCurve, Mode, Outcome};
 use tetrizz::eval::Eval;
 use wasm_bindgen::prelude::*;
-use web_sys::{HtmlCanvasElement, HtmlDivElement};
+use web_sys::{HtmlCanvasElement, HtmlDivElement, HtmlInputElement, HtmlSelectElement};
 use web_time::Instant;
 
 use crate::fps::FPSCounter;
@@ -25,28 +28,29 @@ pub async fn main() -> Result<(), JsValue> {
     let default_skin = "https://i.imgur.com/zjItrsg.png";
     let skin = graphics::load_skin(default_skin).await?;
     let board = doc.get_element_by_id("board").unwrap().dyn_into::<web_sys::HtmlCanvasElement>()?;
+    let gauge = doc.get_element_by_id("gauge").unwrap().dyn_into::<HtmlCanvasElement>()?;
     let hold = doc.get_element_by_id("hold").unwrap().dyn_into::<web_sys::HtmlCanvasElement>()?;
     let queue = doc.get_element_by_id("queue").unwrap().dyn_into::<HtmlCanvasElement>()?;
     let timer_div = doc.get_element_by_id("timer").unwrap().dyn_into::<HtmlDivElement>()?;
     let fps_div = doc.get_element_by_id("fps").unwrap().dyn_into::<HtmlDivElement>()?;
     let spins_div = doc.get_element_by_id("spins").unwrap().dyn_into::<HtmlDivElement>()?;
+    let clear_div = doc.get_element_by_id("clear").unwrap().dyn_into::<HtmlDivElement>()?;
+    let chains_div = doc.get_element_by_id("chains").unwrap().dyn_into::<HtmlDivElement>()?;
+    let level_div = doc.get_element_by_id("level").unwrap().dyn_into::<HtmlDivElement>()?;
+    let lines_div = doc.get_element_by_id("lines").unwrap().dyn_into::<HtmlDivElement>()?;
+    let score_div = doc.get_element_by_id("score").unwrap().dyn_into::<HtmlDivElement>()?;
+    let attack_div = doc.get_element_by_id("attack").unwrap().dyn_into::<HtmlDivElement>()?;
+    let apm_div = doc.get_element_by_id("apm").unwrap().dyn_into::<HtmlDivElement>()?;
+    let results_div = doc.get_element_by_id("results").unwrap().dyn_into::<HtmlDivElement>()?;
     let right_info_div =
         doc.get_element_by_id("right-info").unwrap().dyn_into::<HtmlDivElement>()?;
-    let config = Config {
-        das: 6,
-        arr: 0,
-        gravity: Some(60),
-        soft_drop: 1,
-        lock_delay: (60, 300, 1200),
-        ghost: true,
-    };
 
     let (tx, rx) = channel();
     input::init_input_handlers(tx)?;
     let (mut raf_loop, _canceler) = wasm_repeated_animation_frame::RafLoop::new();
     let mut fps = fps::FPSCounter::new();
-    let mut game = Game::new(config);
-    game.mode = tetris::Mode::Sprint { target_lines: 40 };
+    let mut game = Game::new(selected_config());
+    game.mode = selected_mode();
     // game.mode = tetris::Mode::TrainingLab {
     //     search: false,
     //     // lookahead: Some(Lookahead::new(3, 30)),
@@ -56,7 +60,7 @@ pub async fn main() -> Result<(), JsValue> {
     info!("starting event loop, why won't you work!?");
     info!("mode: {:?}", game.mode);
     let sound = SoundPlayer::<NullSink>::default();
-    game.start(None, &sound);
+    game.start(selected_seed(), &sound);
     let mut new_piece = false;
     let eval = &tetrizz::eval::Eval::new(
         -79.400375,
@@ -83,6 +87,7 @@ pub async fn main() -> Result<(), JsValue> {
             run_loop(
                 &mut game,
                 &board,
+                &gauge,
                 &queue,
                 &hold,
                 &skin,
@@ -91,6 +96,14 @@ pub async fn main() -> Result<(), JsValue> {
                 &fps_div,
                 &right_info_div,
                 &spins_div,
+                &clear_div,
+                &chains_div,
+                &level_div,
+                &lines_div,
+                &score_div,
+                &attack_div,
+                &apm_div,
+                &results_div,
                 &rx,
                 &sound,
                 eval,
@@ -102,10 +115,71 @@ pub async fn main() -> Result<(), JsValue> {
     Ok(())
 }
 
+/// The settings picked in the page
+fn selected_config() -> Config {
+    let doc = web_sys::window().unwrap().document().unwrap();
+    let select = |id| {
+        let select = doc.get_element_by_id(id).unwrap();
+        select.dyn_into::<HtmlSelectElement>().unwrap().value()
+    };
+    let number = |id| {
+        doc.get_element_by_id(id)
+            .and_then(|e| e.dyn_into::<HtmlInputElement>().ok())
+            .and_then(|input| input.value().parse::<u8>().ok())
+    };
+    Config {
+        das: 6,
+        arr: 0,
+        gravity: Some(60),
+        soft_drop: 1,
+        lock_delay: (60, 300, 1200),
+        ghost: true,
+        are: 0,
+        line_clear_delay: 0,
+        spin_rule: select("spin-select").parse().unwrap_or_default(),
+        attack_table: select("garbage-select").parse().unwrap_or_default(),
+        garbage_delay: 20,
+        width: number("board-width").map_or(10, |n| n.clamp(4, MAX_WIDTH as u8)),
+        height: number("board-height").map_or(20, |n| n.clamp(4, MAX_HEIGHT as u8)),
+        randomizer: select("randomizer-select").parse().unwrap_or_default(),
+        no_sz_start: None,
+        rotation_system: select("kick-select").parse().unwrap_or_default(),
+        flip_kicks: select("flip-select").parse().unwrap_or_default(),
+        lock_out: select("lock-out-select").parse().unwrap_or_default(),
+        hold: select("hold-select").parse().unwrap_or_default(),
+        hold_slots: number("hold-slots").map_or(1, |n| n.clamp(1, MAX_HOLD_SLOTS)),
+        preview: number("preview").map_or(5, |n| n.min(MAX_PREVIEW)),
+    }
+}
+
+fn selected_mode() -> Mode {
+    let doc = web_sys::window().unwrap().document().unwrap();
+    let select = doc.get_element_by_id("mode-select").unwrap();
+    match select.dyn_into::<HtmlSelectElement>().unwrap().value().as_str() {
+        "cheese" => Mode::Cheese { target_lines: 10, height: 10 },
+        "marathon" => Mode::Marathon {
+            target_lines: 150,
+            gravity: GravityCurve::Guideline,
+            lock_delay: vec![],
+        },
+        "master" => Mode::Master { level: 0 },
+        "ultra" => Mode::Ultra { duration: 180 },
+        _ => Mode::Sprint { target_lines: 40 },
+    }
+}
+
+/// The seed typed in, or a random one when it's left blank
+fn selected_seed() -> Option<u64> {
+    let doc = web_sys::window().unwrap().document().unwrap();
+    let input = doc.get_element_by_id("seed")?.dyn_into::<HtmlInputElement>().ok()?;
+    seed_from_str(input.value().trim()).filter(|&seed| seed != 0)
+}
+
 #[allow(clippy::too_many_arguments)]
 fn run_loop(
     game: &mut Game,
     board: &HtmlCanvasElement,
+    gauge: &HtmlCanvasElement,
     queue: &HtmlCanvasElement,
     hold: &HtmlCanvasElement,
     skin: &Skin,
@@ -114,6 +188,14 @@ fn run_loop(
     fps: &HtmlDivElement,
     line_count: &HtmlDivElement,
     spins: &HtmlDivElement,
+    clear: &HtmlDivElement,
+    chains: &HtmlDivElement,
+    level: &HtmlDivElement,
+    lines: &HtmlDivElement,
+    score: &HtmlDivElement,
+    attack: &HtmlDivElement,
+    apm: &HtmlDivElement,
+    results: &HtmlDivElement,
     rx: &Receiver<Event>,
     sound: &SoundPlayer<impl Sink>,
     eval: &Eval,
@@ -122,24 +204,32 @@ fn run_loop(
     let now = Instant::now();
     fps.set_text_content(Some(&format!("fps: {}", fps_counter.tick(now))));
 
-    let t = if let Some(start_time) = game.start_time {
-        game.end_time.unwrap_or(now).duration_since(start_time).as_secs_f64()
-    } else {
-        0.0
-    };
-    timer.set_text_content(Some(&format!("{t:.2}")));
+    let t = game.elapsed(now).as_secs_f64();
+    // timed modes count down instead
+    let shown = game.time_remaining(now).map_or(t, |remaining| remaining.as_secs_f64());
+    timer.set_text_content(Some(&format!("{shown:.2}")));
+    let level_text = game.level().map(|level| format!("lvl {level}"));
+    level.set_text_content(level_text.as_deref());
+    lines.set_text_content(Some(&format!("{} lines", game.lines)));
+    score.set_text_content(Some(&format!("{} pts", game.score)));
+    attack.set_text_content(Some(&format!("{} atk", game.attack)));
+    let attack_per_minute = if t > 0.0 { game.attack as f64 * 60.0 / t } else { 0.0 };
+    apm.set_text_content(Some(&format!("{attack_per_minute:.1} apm")));
 
-    if let tetris::Mode::Sprint { target_lines: target } = game.mode {
-        line_count.set_text_content(Some(&format!("{}", target.saturating_sub(game.lines))));
+    if let Some(remaining) = game.lines_remaining() {
+        line_count.set_text_content(Some(&format!("{remaining}")));
     }
     while let Ok(e) = rx.try_recv() {
         use tetris::{Event::*, GameState::*, InputEvent::*};
         if let Input(Restart) = e {
-            game.start(None, sound);
+            // settings changed in the page take effect from the next game
+            game.config = selected_config();
+            game.mode = selected_mode();
+            game.start(selected_seed(), sound);
             break;
         }
-        info!("search enabled: {}, new_piece: {}", game.mode.search_enabled(), new_piece);
-        if game.mode.search_enabled() && *new_piece {
+        info!("search enabled: {}, new_piece: {}", game.search_enabled(), new_piece);
+        if game.search_enabled() && *new_piece && game.state == Running {
             // call search algorithm
             log::info!("upcoming: {:?}", game.upcomming);
             log::info!("hold: {:?}", game.hold);
@@ -148,16 +238,19 @@ fn run_loop(
             log::info!("queue: {queue:?}");
             log::info!("hold: {:?}", tetrizz_game.hold);
             let search_loc = tetrizz::movegen::movegen(&tetrizz_game, queue[0]);
+            let depth = queue.len().min(7);
             let heap = tetrizz::beam_search::search_results(
                 &tetrizz_game,
                 &search_loc,
                 queue,
                 eval,
-                7,
+                depth,
                 3000,
             );
             let mut spins = vec![];
-            for node in heap.iter() {
+            let usable =
+                heap.iter().filter(|node| game.can_follow(node.moves.iter().map(|m| m.0.piece)));
+            for node in usable {
                 for (m, placement_info) in node.moves.iter() {
                     if placement_info.lines_cleared > 0 {
                         if m.spun {
@@ -172,31 +265,55 @@ fn run_loop(
             game.spins = spins;
             *new_piece = false;
         }
-        if game.state == Running
-            || game.state == Startup
-                && matches!(e, Input(PressLeft | PressRight | ReleaseLeft | ReleaseRight))
+        if let Input(input) = e
+            && game.accepts(input)
         {
-            *new_piece |= game.handle(e, now, sound);
+            *new_piece |= game.handle(e, now, sound).is_some();
         }
     }
-    if game.state == GameState::Done {
+    if game.state.outcome().is_some() {
         game.timers.clear();
     }
-    while let Some(&(t, timer_event)) = game.timers.front() {
-        if t < now {
-            game.timers.pop_front();
-            game.handle(Event::Timer(timer_event), now, sound);
-        } else {
-            break;
-        }
-    }
+    *new_piece |= !game.advance(now, sound).is_empty();
 
     graphics::draw_board(game, board, skin, t).unwrap();
+    graphics::draw_gauge(game, gauge).unwrap();
     // could do these only when needed instead of every frame if we wanted
-    graphics::draw_queue(game, queue, skin, 5).unwrap();
+    graphics::draw_queue(game, queue, skin).unwrap();
     graphics::draw_hold(game, hold, skin).unwrap();
 
     let spin_text = game.display_spins().to_string();
     info!("spins: {spin_text}");
     spins.set_text_content(Some(&spin_text));
+    let clear_text = if game.paused.is_some() {
+        Some("paused".to_owned())
+    } else if let Some(Outcome::ToppedOut(reason)) = game.state.outcome() {
+        Some(reason.to_string())
+    } else {
+        game.last_placement.as_ref().map(ToString::to_string)
+    };
+    clear.set_text_content(clear_text.as_deref());
+    let mut chain_text = String::new();
+    if game.combo > 1 {
+        chain_text += &format!("{} combo\n", game.combo - 1);
+    }
+    if game.b2b > 1 {
+        chain_text += &format!("b2b x{}", game.b2b - 1);
+    }
+    chains.set_text_content(Some(&chain_text));
+    // stays empty, and so hidden, until the game is over
+    let results_text = match game.state {
+        GameState::Done(GameResult { outcome, stats }) => Some(format!(
+            "{outcome}\n{:.2}s\n{} pieces, {:.2} pps\n{} lines\n{} atk, {:.1} apm\n{} pts",
+            stats.time.as_secs_f64(),
+            stats.pieces,
+            stats.pps(),
+            stats.lines,
+            stats.attack,
+            stats.apm(),
+            stats.score,
+        )),
+        _ => None,
+    };
+    results.set_text_content(results_text.as_deref());
 }